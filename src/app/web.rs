@@ -1 +1,511 @@
+//! HTTP API server that keeps a single [`Ecam`] connection open and exposes it over JSON endpoints.
+//!
+//! | Method | Path          | Description                                              |
+//! |--------|---------------|----------------------------------------------------------|
+//! | GET    | `/status`     | The current [`EcamStatus`] of the machine                |
+//! | GET    | `/events`     | Server-Sent Events stream of every status change         |
+//! | GET    | `/recipes`    | The recipes (and ingredient ranges) stored on the device |
+//! | POST   | `/brew`       | Validate and start brewing a beverage                    |
+//! | POST   | `/stop`       | Stop a beverage that is being brewed                     |
+//! | POST   | `/power-on`   | Turn the machine on and wait for it to become ready      |
+//! | GET    | `/pin`        | Whether the machine is PIN-locked                        |
+use std::convert::Infallible;
+use std::net::SocketAddr;
 
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio_stream::{Stream, StreamExt};
+
+use longshot::ecam::{Ecam, EcamError, EcamStatus};
+use longshot::{operations::*, protocol::*};
+
+/// An error returned to the HTTP client as `{"error": "..."}`.
+#[derive(Debug)]
+pub struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<EcamError> for ApiError {
+    fn from(e: EcamError) -> Self {
+        match e {
+            EcamError::NotFound => ApiError(StatusCode::NOT_FOUND, e.to_string()),
+            EcamError::InvalidArgument(_) => ApiError(StatusCode::BAD_REQUEST, e.to_string()),
+            EcamError::IngredientValidation(_) => {
+                ApiError(StatusCode::UNPROCESSABLE_ENTITY, e.to_string())
+            }
+            EcamError::NotReady(_) => ApiError(StatusCode::CONFLICT, e.to_string()),
+            EcamError::Disconnected => ApiError(StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+            EcamError::Timeout => ApiError(StatusCode::GATEWAY_TIMEOUT, e.to_string()),
+            e => ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+}
+
+/// JSON form of [`EcamStatus`].
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percentage: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alarm: Option<String>,
+}
+
+impl From<EcamStatus> for StatusResponse {
+    fn from(status: EcamStatus) -> Self {
+        let (status, percentage, alarm) = match status {
+            EcamStatus::StandBy => ("standby", None, None),
+            EcamStatus::TurningOn(p) => ("turningon", Some(p), None),
+            EcamStatus::ShuttingDown(p) => ("shuttingdown", Some(p), None),
+            EcamStatus::Ready => ("ready", None, None),
+            EcamStatus::Busy(p) => ("busy", Some(p), None),
+            EcamStatus::Cleaning(p) => ("cleaning", Some(p), None),
+            EcamStatus::Descaling => ("descaling", None, None),
+            EcamStatus::Alarm(alarm) => ("alarm", None, Some(format!("{:?}", alarm))),
+            EcamStatus::Fetching(p) => ("fetching", Some(p), None),
+        };
+        StatusResponse {
+            status,
+            percentage,
+            alarm,
+        }
+    }
+}
+
+/// JSON form of a [`MonitorStatus`], sent for each event on `GET /events`.
+#[derive(Debug, Serialize)]
+struct MonitorEvent {
+    #[serde(flatten)]
+    status: StatusResponse,
+    state: String,
+    accessory: String,
+    switches: Vec<String>,
+    alarms: Vec<String>,
+    progress: u8,
+    /// The percentage reported by the machine, which may differ from the percentage of the status.
+    raw_percentage: u8,
+}
+
+impl From<&MonitorStatus> for MonitorEvent {
+    fn from(response: &MonitorStatus) -> Self {
+        MonitorEvent {
+            status: EcamStatus::from_status(response).into(),
+            state: format!("{:?}", response.state),
+            accessory: format!("{:?}", response.accessory),
+            switches: response
+                .switches
+                .set()
+                .iter()
+                .map(|x| format!("{:?}", x))
+                .collect(),
+            alarms: response
+                .alarms
+                .set()
+                .iter()
+                .map(|x| format!("{:?}", x))
+                .collect(),
+            progress: response.progress,
+            raw_percentage: response.percentage,
+        }
+    }
+}
+
+/// JSON body accepted by `POST /brew`. Ingredient values use the same format as the `brew` command-line arguments.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct BrewRequest {
+    beverage: String,
+    profile: Option<String>,
+    coffee: Option<u16>,
+    milk: Option<u16>,
+    hotwater: Option<u16>,
+    taste: Option<String>,
+    temperature: Option<String>,
+    #[serde(default)]
+    allow_defaults: bool,
+    #[serde(default)]
+    force: bool,
+}
+
+impl BrewRequest {
+    fn ingredients(&self) -> Result<Vec<BrewIngredientInfo>, ApiError> {
+        let args = [
+            ("coffee", self.coffee.map(|x| x.to_string())),
+            ("milk", self.milk.map(|x| x.to_string())),
+            ("hotwater", self.hotwater.map(|x| x.to_string())),
+            ("taste", self.taste.clone()),
+            ("temperature", self.temperature.clone()),
+        ];
+        let mut ingredients = vec![];
+        for (arg, value) in args {
+            if let Some(value) = value {
+                ingredients.push(BrewIngredientInfo::from_arg(arg, &value).ok_or_else(|| {
+                    ApiError(
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid value '{}' for '{}'", value, arg),
+                    )
+                })?);
+            }
+        }
+        Ok(ingredients)
+    }
+
+    fn mode(&self) -> IngredientCheckMode {
+        match (self.allow_defaults, self.force) {
+            (_, true) => IngredientCheckMode::Force,
+            (true, false) => IngredientCheckMode::AllowDefaults,
+            (false, false) => IngredientCheckMode::Strict,
+        }
+    }
+}
+
+fn ingredient_to_json(ingredient: &IngredientRangeInfo) -> Value {
+    let name = ingredient.ingredient().to_arg_string();
+    match ingredient {
+        IngredientRangeInfo::Coffee(min, value, max)
+        | IngredientRangeInfo::Milk(min, value, max)
+        | IngredientRangeInfo::HotWater(min, value, max) => {
+            json!({ "ingredient": name, "min": min, "default": value, "max": max })
+        }
+        IngredientRangeInfo::Taste(taste) => {
+            json!({ "ingredient": name, "default": taste.to_arg_string() })
+        }
+        IngredientRangeInfo::Temperature(temperature) => {
+            json!({ "ingredient": name, "default": temperature.to_arg_string() })
+        }
+        IngredientRangeInfo::Accessory(accessory) => {
+            json!({ "ingredient": name, "default": accessory.to_arg_string() })
+        }
+        IngredientRangeInfo::Inversion(value, fixed) | IngredientRangeInfo::Brew2(value, fixed) => {
+            json!({ "ingredient": name, "default": value, "fixed": fixed })
+        }
+    }
+}
+
+async fn status(State(ecam): State<Ecam>) -> Result<Json<StatusResponse>, ApiError> {
+    Ok(Json(ecam.current_state().await?.into()))
+}
+
+/// Streams a `status` event each time the device reports a change.
+async fn events(
+    State(ecam): State<Ecam>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let stream = ecam.status_stream().await?.map(|response| {
+        Ok(Event::default()
+            .event("status")
+            .json_data(MonitorEvent::from(&response))
+            .expect("Failed to serialize event"))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Query parameters for endpoints that accept a profile number or name.
+#[derive(Debug, Default, Deserialize)]
+struct ProfileQuery {
+    profile: Option<String>,
+}
+
+async fn resolve(ecam: &Ecam, profile: &Option<String>) -> Result<u8, ApiError> {
+    match profile {
+        Some(profile) => Ok(resolve_profile(ecam.clone(), profile).await?),
+        None => Ok(DEFAULT_PROFILE),
+    }
+}
+
+async fn recipes(
+    State(ecam): State<Ecam>,
+    Query(query): Query<ProfileQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let profile = resolve(&ecam, &query.profile).await?;
+    let list = list_recipies_for(ecam, profile, None).await?;
+    let recipes = list
+        .recipes
+        .iter()
+        .map(|recipe| {
+            json!({
+                "beverage": recipe.beverage.to_arg_string(),
+                "ingredients": recipe.fetch_ingredients().iter().map(ingredient_to_json).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(Json(json!({ "profile": profile, "recipes": recipes })))
+}
+
+fn parse_beverage(beverage: &str) -> Result<EcamBeverageId, ApiError> {
+    EcamBeverageId::lookup_by_name_case_insensitive(beverage).ok_or_else(|| {
+        ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("Unknown beverage '{}'", beverage),
+        )
+    })
+}
+
+/// Validates the recipe and then starts brewing in the background, returning `202 Accepted` with the computed recipe.
+/// Progress can be followed with `GET /status`.
+async fn brew_beverage(
+    State(ecam): State<Ecam>,
+    Json(request): Json<BrewRequest>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let beverage = parse_beverage(&request.beverage)?;
+    let ingredients = request.ingredients()?;
+    let profile = resolve(&ecam, &request.profile).await?;
+    let recipe =
+        validate_brew(ecam.clone(), profile, beverage, ingredients, request.mode()).await?;
+    match ecam.current_state().await? {
+        EcamStatus::Ready => {}
+        s => return Err(EcamError::NotReady(s).into()),
+    }
+    let response = json!({
+        "beverage": beverage.to_arg_string(),
+        "recipe": recipe.iter().map(|r| json!({ "ingredient": format!("{:?}", r.ingredient), "value": r.value })).collect::<Vec<_>>(),
+    });
+    tokio::spawn(async move {
+        if let Err(e) = brew(ecam, false, beverage, recipe, DEFAULT_BREW_TIMEOUT).await {
+            longshot::display::log(
+                longshot::display::LogLevel::Error,
+                &format!("Brew failed: {}", e),
+            );
+        }
+    });
+    Ok((StatusCode::ACCEPTED, Json(response)))
+}
+
+/// JSON body accepted by `POST /stop`.
+#[derive(Debug, Deserialize)]
+struct StopRequest {
+    beverage: String,
+}
+
+/// Stops the given beverage and waits for the machine to stop dispensing.
+async fn stop(
+    State(ecam): State<Ecam>,
+    Json(request): Json<StopRequest>,
+) -> Result<Json<Value>, ApiError> {
+    let beverage = parse_beverage(&request.beverage)?;
+    cancel_brew(ecam, beverage, DEFAULT_CANCEL_TIMEOUT).await?;
+    Ok(Json(
+        json!({ "beverage": beverage.to_arg_string(), "stopped": true }),
+    ))
+}
+
+async fn power(State(ecam): State<Ecam>) -> Result<Json<Value>, ApiError> {
+    power_on(ecam, false, false, true, DEFAULT_POWER_ON_TIMEOUT).await?;
+    Ok(Json(json!({ "on": true })))
+}
+
+async fn pin_status(State(ecam): State<Ecam>) -> Result<Json<Value>, ApiError> {
+    let pin = read_pin_state(ecam).await?;
+    Ok(Json(json!({ "locked": pin.enabled })))
+}
+
+/// Creates the [`Router`] for the API, bound to the given [`Ecam`].
+pub fn router(ecam: Ecam) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        .route("/events", get(events))
+        .route("/recipes", get(recipes))
+        .route("/brew", post(brew_beverage))
+        .route("/stop", post(stop))
+        .route("/power-on", post(power))
+        .route("/pin", get(pin_status))
+        .with_state(ecam)
+}
+
+/// Serves the API on the given address until the connection to the device is lost.
+pub async fn serve(ecam: Ecam, addr: SocketAddr) -> Result<(), EcamError> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    longshot::info!("Listening on http://{}", listener.local_addr()?);
+    let alive = ecam.clone();
+    axum::serve(listener, router(ecam))
+        .with_graceful_shutdown(async move {
+            while alive.is_alive() {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use longshot::ecam::{EcamId, get_ecam_simulator};
+    use tower::ServiceExt;
+
+    async fn simulator() -> Ecam {
+        let driver = get_ecam_simulator(&EcamId::Simulator("sim[on]".into()))
+            .await
+            .expect("Failed to create simulator");
+        Ecam::new(Box::new(driver), false).await
+    }
+
+    async fn call(ecam: Ecam, request: Request<Body>) -> (StatusCode, Value) {
+        let response = router(ecam).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn post_json(uri: &str, body: Value) -> Request<Body> {
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_status() {
+        let ecam = simulator().await;
+        let (status, body) = call(ecam, Request::get("/status").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "status": "ready" }));
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let ecam = simulator().await;
+        let response = router(ecam.clone())
+            .oneshot(Request::get("/events").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body();
+        let frame = body.frame().await.unwrap().unwrap();
+        let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+        assert_eq!(
+            text,
+            concat!(
+                "event: status\n",
+                r#"data: {"status":"ready","state":"ReadyOrDispensing","accessory":"None","#,
+                r#""switches":["WaterSpout"],"alarms":[],"progress":0,"raw_percentage":0}"#,
+                "\n\n"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recipes() {
+        let ecam = simulator().await;
+        let (status, body) =
+            call(ecam, Request::get("/recipes").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let espresso = body["recipes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["beverage"] == "espressocoffee")
+            .expect("Expected an espresso recipe");
+        assert_eq!(
+            espresso["ingredients"][0],
+            json!({ "ingredient": "coffee", "min": 20, "default": 40, "max": 180 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recipes_for_profile() {
+        let ecam = simulator().await;
+        let (status, body) = call(
+            ecam.clone(),
+            Request::get("/recipes?profile=PROFILE%203")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["profile"], 3);
+        assert!(!body["recipes"].as_array().unwrap().is_empty());
+        let (status, _) = call(
+            ecam,
+            Request::get("/recipes?profile=nobody")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_brew_rejected() {
+        let ecam = simulator().await;
+        let (status, _) = call(
+            ecam.clone(),
+            post_json("/brew", json!({ "beverage": "not-a-drink" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = call(
+            ecam,
+            post_json(
+                "/brew",
+                json!({ "beverage": "espressocoffee", "coffee": 1000 }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let error = body["error"].as_str().unwrap();
+        assert!(error.contains("missing --taste"), "{}", error);
+        assert!(
+            error.contains("Coffee value out of range (20<=1000<=180)"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_brew_accepted() {
+        let ecam = simulator().await;
+        let (status, body) = call(
+            ecam,
+            post_json(
+                "/brew",
+                json!({ "beverage": "espressocoffee", "allow-defaults": true }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body["beverage"], "espressocoffee");
+    }
+
+    #[tokio::test]
+    async fn test_stop() {
+        let ecam = simulator().await;
+        let (status, _) = call(
+            ecam.clone(),
+            post_json("/stop", json!({ "beverage": "not-a-drink" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = call(
+            ecam,
+            post_json("/stop", json!({ "beverage": "espressocoffee" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["stopped"], true);
+    }
+
+    #[tokio::test]
+    async fn test_pin() {
+        let ecam = simulator().await;
+        let (status, body) = call(
+            ecam.clone(),
+            Request::get("/pin").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "locked": false }));
+    }
+}
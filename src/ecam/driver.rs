@@ -13,13 +13,13 @@ pub enum EcamDriverOutput {
 /// for some tips on making async trait functions.
 pub trait EcamDriver: Send + Sync {
     /// Read one item from the ECAM.
-    fn read(&self) -> AsyncFuture<Option<EcamDriverOutput>>;
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>>;
 
     /// Write one item to the ECAM.
-    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<()>;
+    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<'_, ()>;
 
     /// Returns true if the driver is alive.
-    fn alive(&self) -> AsyncFuture<bool>;
+    fn alive(&self) -> AsyncFuture<'_, bool>;
 
     /// Scan for the first matching device.
     fn scan<'a>() -> AsyncFuture<'a, (String, EcamId)>
@@ -52,7 +52,7 @@ mod test {
     }
 
     impl EcamDriver for EcamTest {
-        fn read(&self) -> crate::prelude::AsyncFuture<Option<EcamDriverOutput>> {
+        fn read(&self) -> crate::prelude::AsyncFuture<'_, Option<EcamDriverOutput>> {
             Box::pin(async {
                 if self.read_items.read().is_empty() {
                     Ok(None)
@@ -62,12 +62,12 @@ mod test {
             })
         }
 
-        fn write(&self, data: EcamDriverPacket) -> crate::prelude::AsyncFuture<()> {
+        fn write(&self, data: EcamDriverPacket) -> crate::prelude::AsyncFuture<'_, ()> {
             self.write_items.write().push(data);
             Box::pin(async { Ok(()) })
         }
 
-        fn alive(&self) -> AsyncFuture<bool> {
+        fn alive(&self) -> AsyncFuture<'_, bool> {
             Box::pin(async { Ok(true) })
         }
 
@@ -135,15 +135,15 @@ impl EcamBT {
 }
 
 impl EcamDriver for EcamBT {
-    fn read<'a>(&self) -> AsyncFuture<Option<EcamDriverOutput>> {
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>> {
         Box::pin(self.notifications.recv())
     }
 
-    fn write<'a>(&self, data: EcamDriverPacket) -> AsyncFuture<()> {
+    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<'_, ()> {
         Box::pin(self.peripheral.write(data.packetize()))
     }
 
-    fn alive(&self) -> AsyncFuture<bool> {
+    fn alive(&self) -> AsyncFuture<'_, bool> {
         Box::pin(self.peripheral.is_alive())
     }
 
@@ -249,7 +249,7 @@ impl EcamPeripheral {
     pub async fn validate(peripheral: Peripheral) -> Result<Option<Self>, EcamError> {
         let properties = peripheral.properties().await?;
         let is_connected = peripheral.is_connected().await?;
-        let properties = properties.ok_or(EcamError::Unknown)?;
+        let properties = properties.ok_or(EcamError::NotFound)?;
         if let Some(local_name) = properties.local_name {
             if !is_connected {
                 peripheral.connect().await?
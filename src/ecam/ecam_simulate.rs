@@ -3,8 +3,9 @@ use tokio::sync::Mutex;
 use crate::ecam::{EcamDriver, EcamDriverOutput, EcamError};
 use crate::prelude::*;
 use crate::protocol::{
-    EcamAccessory, EcamBeverageId, EcamDriverPacket, EcamMachineState, EcamMachineSwitch,
-    EcamRequestId, MonitorV2Response, PartialEncode, SwitchSet, hexdump,
+    BeanSystem, EcamAccessory, EcamBeverageId, EcamBeverageTaste, EcamDriverPacket,
+    EcamMachineState, EcamMachineSwitch, EcamRequestId, EcamTemperature, MachineEnumerable,
+    MonitorV2Response, PartialEncode, Statistic, SwitchSet, WideStringWithIcon, hexdump,
 };
 
 use super::EcamId;
@@ -116,39 +117,123 @@ fn get_recipes(beverage: EcamBeverageId) -> Option<(Vec<u8>, Vec<u8>)> {
 }
 
 impl EcamDriver for EcamSimulate {
-    fn read(&self) -> AsyncFuture<Option<EcamDriverOutput>> {
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>> {
         Box::pin(async {
             let packet = self.rx.lock().await.recv().await;
             Ok(packet)
         })
     }
 
-    fn write(&self, data: crate::protocol::EcamDriverPacket) -> AsyncFuture<()> {
+    fn write(&self, data: crate::protocol::EcamDriverPacket) -> AsyncFuture<'_, ()> {
         trace_packet!("{{host->device}} {}", hexdump(&data.bytes));
         Box::pin(async move {
             if data.bytes[0] == EcamRequestId::RecipeQuantityRead as u8 {
-                let mut packet = vec![data.bytes[0], 0xf0, 1, data.bytes[3]];
-                if let Ok(beverage) = data.bytes[3].try_into() {
-                    if let Some((recipe, _)) = get_recipes(beverage) {
-                        packet = [packet, recipe].concat();
-                    }
+                let mut packet = vec![data.bytes[0], 0xf0, data.bytes[2], data.bytes[3]];
+                if let Ok(beverage) = data.bytes[3].try_into()
+                    && let Some((recipe, _)) = get_recipes(beverage)
+                {
+                    packet = [packet, recipe].concat();
                 }
                 send(&*self.tx.lock().await, packet).await?;
             }
             if data.bytes[0] == EcamRequestId::RecipeMinMaxSync as u8 {
                 let mut packet = vec![data.bytes[0], 0xf0, data.bytes[2]];
-                if let Ok(beverage) = data.bytes[2].try_into() {
-                    if let Some((_, minmax)) = get_recipes(beverage) {
-                        packet = [packet, minmax].concat();
+                if let Ok(beverage) = data.bytes[2].try_into()
+                    && let Some((_, minmax)) = get_recipes(beverage)
+                {
+                    packet = [packet, minmax].concat();
+                }
+                send(&*self.tx.lock().await, packet).await?;
+            }
+            if data.bytes[0] == EcamRequestId::ProfileNameRead as u8 {
+                let mut packet = vec![data.bytes[0], 0xf0];
+                for profile in data.bytes[2]..=data.bytes[3] {
+                    WideStringWithIcon::new(&format!("PROFILE {}", profile), profile)
+                        .partial_encode(&mut packet);
+                }
+                send(&*self.tx.lock().await, packet).await?;
+            }
+            if data.bytes[0] == EcamRequestId::RecipeNameRead as u8 {
+                let mut packet = vec![data.bytes[0], 0xf0];
+                for recipe in data.bytes[2]..=data.bytes[3] {
+                    WideStringWithIcon::new(&format!("CUSTOM {}", recipe), recipe)
+                        .partial_encode(&mut packet);
+                }
+                send(&*self.tx.lock().await, packet).await?;
+            }
+            if data.bytes[0] == EcamRequestId::Checksum as u8 {
+                send(
+                    &*self.tx.lock().await,
+                    vec![data.bytes[0], 0xf0, 0x12, 0x34],
+                )
+                .await?;
+            }
+            if data.bytes[0] == EcamRequestId::ParameterReadExt as u8 {
+                // Parameter memory reads back as zeroes, apart from the parameter's own address
+                let mut packet = vec![data.bytes[0], 0xf0, data.bytes[2], data.bytes[3]];
+                packet.resize(packet.len() + data.bytes[4] as usize, 0);
+                *packet.last_mut().unwrap() = data.bytes[3];
+                send(&*self.tx.lock().await, packet).await?;
+            }
+            if data.bytes[0] == EcamRequestId::StatisticsRead as u8 {
+                // Statistics 1-12, returned in batches of up to 9 from the requested statistic onwards
+                const LAST_STAT: u16 = 12;
+                let start = u16::from_be_bytes([data.bytes[2], data.bytes[3]]).max(1);
+                let mut packet = vec![data.bytes[0], 0xf0];
+                if start < LAST_STAT {
+                    for stat in (start..=LAST_STAT).take((data.bytes[4] as usize).min(9)) {
+                        Statistic {
+                            stat,
+                            value: stat as u32 * 100,
+                        }
+                        .partial_encode(&mut packet);
+                    }
+                }
+                send(&*self.tx.lock().await, packet).await?;
+            }
+            if data.bytes[0] == EcamRequestId::PinRead as u8 {
+                // The simulator is never PIN-locked
+                send(&*self.tx.lock().await, vec![data.bytes[0], 0xf0, 0, 0, 0]).await?;
+            }
+            if data.bytes[0] == EcamRequestId::RecipePriorityRead as u8 {
+                let mut packet = vec![data.bytes[0], 0xf0];
+                packet.extend(EcamBeverageId::all().take(8).map(u8::from));
+                send(&*self.tx.lock().await, packet).await?;
+            }
+            if data.bytes[0] == EcamRequestId::BeanSystemRead as u8 {
+                // The simulator has seven bean systems
+                let mut packet = vec![data.bytes[0], 0xf0];
+                for bean in data.bytes[2]..=data.bytes[3].min(7) {
+                    BeanSystem {
+                        name: format!("BEAN {}", bean),
+                        temperature: EcamTemperature::Mid.into(),
+                        taste: EcamBeverageTaste::Normal.into(),
+                        grind: bean,
                     }
+                    .partial_encode(&mut packet);
                 }
                 send(&*self.tx.lock().await, packet).await?;
             }
+            if data.bytes[0] == EcamRequestId::ProfileNameWrite as u8
+                || data.bytes[0] == EcamRequestId::ProfileSelection as u8
+                || data.bytes[0] == EcamRequestId::BeanSystemSelect as u8
+                || data.bytes[0] == EcamRequestId::SetFavoriteBeverages as u8
+                || data.bytes[0] == EcamRequestId::RecipeNameWrite as u8
+            {
+                send(&*self.tx.lock().await, vec![data.bytes[0], 0xf0]).await?;
+            }
+            if data.bytes[0] == EcamRequestId::BeverageDispensingMode as u8 {
+                send(&*self.tx.lock().await, vec![data.bytes[0], 0xf0, 1, 0]).await?;
+            }
+            if data.bytes[0] == EcamRequestId::ParameterWrite as u8 {
+                // Acknowledge the write by echoing it back
+                send(&*self.tx.lock().await, data.bytes.clone()).await?;
+            }
             Ok(())
         })
     }
 
-    fn alive(&self) -> AsyncFuture<bool> {
+    fn alive(&self) -> AsyncFuture<'_, bool> {
         Box::pin(async { Ok(true) })
     }
 
@@ -180,7 +265,7 @@ fn make_simulated_response(state: EcamMachineState, progress: u8, percentage: u8
 
 fn eat_errors_with_warning<T: std::fmt::Debug>(e: T) -> EcamError {
     warning!("{:?}", e);
-    EcamError::Unknown
+    EcamError::Disconnected
 }
 
 async fn send_output(
@@ -209,7 +294,9 @@ pub async fn get_ecam_simulator(id: &EcamId) -> Result<impl EcamDriver + use<>,
     const DELAY: Duration = Duration::from_millis(250);
     send_output(&tx, EcamDriverOutput::Ready).await?;
     let tx_out = tx.clone();
-    let on = simulator.ends_with("[on]");
+    // [rinse] runs a rinse cycle in place of dispensing a beverage
+    let rinse = simulator.ends_with("[rinse]");
+    let on = rinse || simulator.ends_with("[on]");
     trace_packet!("Initializing simulator: {}", simulator);
     tokio::spawn(async move {
         if !on {
@@ -244,13 +331,14 @@ pub async fn get_ecam_simulator(id: &EcamId) -> Result<impl EcamDriver + use<>,
             tokio::time::sleep(DELAY).await;
         }
 
-        // Dispensing
+        // Dispensing (or rinsing)
+        let state = if rinse {
+            EcamMachineState::Rinsing
+        } else {
+            EcamMachineState::ReadyOrDispensing
+        };
         for i in 0..25 {
-            send(
-                &tx,
-                make_simulated_response(EcamMachineState::ReadyOrDispensing, i, i * 4),
-            )
-            .await?;
+            send(&tx, make_simulated_response(state, i, i * 4)).await?;
             tokio::time::sleep(DELAY).await;
         }
 
@@ -41,15 +41,15 @@ impl EcamSubprocess {
 }
 
 impl EcamDriver for EcamSubprocess {
-    fn read<'a>(&self) -> AsyncFuture<Option<EcamDriverOutput>> {
+    fn read(&self) -> AsyncFuture<'_, Option<EcamDriverOutput>> {
         Box::pin(self.receiver.recv())
     }
 
-    fn write<'a>(&self, data: EcamDriverPacket) -> AsyncFuture<()> {
+    fn write(&self, data: EcamDriverPacket) -> AsyncFuture<'_, ()> {
         Box::pin(self.write_stdin(data))
     }
 
-    fn alive(&self) -> AsyncFuture<bool> {
+    fn alive(&self) -> AsyncFuture<'_, bool> {
         Box::pin(self.is_alive())
     }
 
@@ -118,6 +118,10 @@ pub async fn connect(id: &EcamId) -> Result<EcamSubprocess, EcamError> {
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
     cmd.kill_on_drop(true);
+    // Keep the pipe out of our process group so that Ctrl+C reaches only us, and we can still send commands (ie: to
+    // stop a beverage) after being interrupted
+    #[cfg(unix)]
+    cmd.process_group(0);
     let mut child = cmd.spawn()?;
     let stdin = Arc::new(Mutex::new(child.stdin.take().expect("stdin was missing")));
 
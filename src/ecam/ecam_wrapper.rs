@@ -1,11 +1,17 @@
 use crate::prelude::*;
 
 use tokio::sync::{Mutex, OwnedSemaphorePermit};
-use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::{BroadcastStream, WatchStream};
 
 use crate::ecam::{EcamDriver, EcamDriverOutput, EcamError};
 use crate::protocol::*;
 
+/// How long [`Ecam::request`] waits for a response before re-sending the request.
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How many times [`Ecam::request`] sends a request before giving up.
+const REQUEST_ATTEMPTS: usize = 3;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum EcamStatus {
     StandBy,
@@ -33,7 +39,7 @@ impl EcamOutput {
             representation: r, ..
         }) = self
         {
-            r
+            r.ok()
         } else {
             None
         }
@@ -62,6 +68,11 @@ impl From<EcamOutput> for EcamDriverOutput {
 
 impl EcamStatus {
     pub fn extract(state: &MonitorV2Response) -> EcamStatus {
+        Self::from_status(&state.clone().into())
+    }
+
+    /// Extracts the status from a [`MonitorStatus`], which may have been decoded from any monitor version.
+    pub fn from_status(state: &MonitorStatus) -> EcamStatus {
         if state.state == EcamMachineState::TurningOn {
             return EcamStatus::TurningOn(state.percentage as usize);
         }
@@ -97,8 +108,8 @@ impl EcamStatus {
         EcamStatus::Ready
     }
 
-    fn matches(&self, state: &MonitorV2Response) -> bool {
-        *self == Self::extract(state)
+    fn matches(&self, state: &MonitorStatus) -> bool {
+        *self == Self::from_status(state)
     }
 }
 
@@ -185,16 +196,35 @@ pub struct Ecam {
 }
 
 struct EcamInternals {
-    last_status: tokio::sync::watch::Receiver<Option<MonitorV2Response>>,
+    last_status: tokio::sync::watch::Receiver<Option<MonitorStatus>>,
     packet_tap: Arc<tokio::sync::broadcast::Sender<EcamOutput>>,
     ready_lock: Arc<tokio::sync::Semaphore>,
     status_interest: StatusInterest,
     dump_packets: bool,
+    probe_legacy_monitors: bool,
     started: bool,
 }
 
 impl Ecam {
+    /// Wraps the driver. The machine is assumed to speak [`MonitorVersion::V2`].
     pub async fn new(driver: Box<dyn EcamDriver>, dump_packets: bool) -> Self {
+        Self::create(driver, dump_packets, false).await
+    }
+
+    /// Wraps the driver, probing the older monitor versions as well as [`MonitorVersion::V2`] for machines that don't
+    /// answer it. The V0/V1 layouts are inferred from [`MonitorV2Response`] and are unconfirmed.
+    pub async fn new_probing_legacy_monitors(
+        driver: Box<dyn EcamDriver>,
+        dump_packets: bool,
+    ) -> Self {
+        Self::create(driver, dump_packets, true).await
+    }
+
+    async fn create(
+        driver: Box<dyn EcamDriver>,
+        dump_packets: bool,
+        probe_legacy_monitors: bool,
+    ) -> Self {
         let driver = Arc::new(driver);
         let (tx, rx) = tokio::sync::watch::channel(None);
         let (txb, _) = tokio::sync::broadcast::channel(100);
@@ -216,6 +246,7 @@ impl Ecam {
             status_interest: StatusInterest::new(),
             started: false,
             dump_packets,
+            probe_legacy_monitors,
         }));
         let alive = Alive::new();
         let ecam_result = Ecam {
@@ -254,7 +285,7 @@ impl Ecam {
 
     async fn operation_loop(
         mut ready_lock_semaphore: Option<OwnedSemaphorePermit>,
-        tx: tokio::sync::watch::Sender<Option<MonitorV2Response>>,
+        tx: tokio::sync::watch::Sender<Option<MonitorStatus>>,
         driver: Arc<Box<dyn EcamDriver>>,
         internals: Arc<Mutex<EcamInternals>>,
         alive: Alive,
@@ -292,14 +323,16 @@ impl Ecam {
                     break;
                 }
                 EcamOutput::Packet(EcamPacket {
-                    representation: Some(Response::MonitorV2(x)),
+                    representation: Ok(response),
                     ..
                 }) => {
-                    if tx.send(Some(x)).is_err() {
-                        warning!("Failed to send a monitor response");
-                        break;
+                    if let Some(status) = MonitorStatus::from_response(&response) {
+                        if tx.send(Some(status)).is_err() {
+                            warning!("Failed to send a monitor response");
+                            break;
+                        }
+                        ready_lock_semaphore.take();
                     }
-                    ready_lock_semaphore.take();
                 }
                 _ => {}
             }
@@ -323,6 +356,18 @@ impl Ecam {
         self.wait_for(|status| state.matches(status), monitor).await
     }
 
+    /// Blocks until the device state reaches our desired state, or returns [`EcamError::Timeout`] if the state is not
+    /// reached in time.
+    pub async fn wait_for_state_with_timeout(
+        &self,
+        state: EcamStatus,
+        monitor: fn(EcamStatus) -> (),
+        timeout: Duration,
+    ) -> Result<(), EcamError> {
+        self.wait_for_with_timeout(|status| state.matches(status), monitor, timeout)
+            .await
+    }
+
     /// Blocks until the device state is not in the undesired state.
     pub async fn wait_for_not_state(
         &self,
@@ -333,10 +378,22 @@ impl Ecam {
             .await
     }
 
+    /// Blocks until the device state is not in the undesired state, or returns [`EcamError::Timeout`] if the device
+    /// remains in that state.
+    pub async fn wait_for_not_state_with_timeout(
+        &self,
+        state: EcamStatus,
+        monitor: fn(EcamStatus) -> (),
+        timeout: Duration,
+    ) -> Result<(), EcamError> {
+        self.wait_for_with_timeout(|status| !state.matches(status), monitor, timeout)
+            .await
+    }
+
     /// Blocks until the state test function returns true.
     pub async fn wait_for<F>(&self, f: F, monitor: fn(EcamStatus) -> ()) -> Result<(), EcamError>
     where
-        F: Fn(&MonitorV2Response) -> bool,
+        F: Fn(&MonitorStatus) -> bool,
     {
         let alive = self.alive.clone();
         let mut internals = self.internals.lock().await;
@@ -345,16 +402,31 @@ impl Ecam {
         drop(internals);
         while alive.is_alive() {
             if let Some(test) = rx.borrow().as_ref() {
-                monitor(EcamStatus::extract(test));
+                monitor(EcamStatus::from_status(test));
                 if f(test) {
                     drop(status_interest);
                     return Ok(());
                 }
             }
-            // TODO: timeout
-            rx.changed().await.map_err(|_| EcamError::Unknown)?;
+            rx.changed().await.map_err(|_| EcamError::Disconnected)?;
         }
-        Err(EcamError::Unknown)
+        Err(EcamError::Disconnected)
+    }
+
+    /// Blocks until the state test function returns true, or returns [`EcamError::Timeout`] if it does not do so
+    /// within the given duration.
+    pub async fn wait_for_with_timeout<F>(
+        &self,
+        f: F,
+        monitor: fn(EcamStatus) -> (),
+        timeout: Duration,
+    ) -> Result<(), EcamError>
+    where
+        F: Fn(&MonitorStatus) -> bool,
+    {
+        tokio::time::timeout(timeout, self.wait_for(f, monitor))
+            .await
+            .map_err(|_| EcamError::Timeout)?
     }
 
     /// Wait for the connection to establish, but not any particular state.
@@ -374,12 +446,12 @@ impl Ecam {
             ready_lock
                 .acquire_owned()
                 .await
-                .map_err(|_| EcamError::Unknown)?,
+                .map_err(|_| EcamError::Disconnected)?,
         );
         let ret = if let Some(test) = rx.borrow().as_ref() {
-            Ok(EcamStatus::extract(test))
+            Ok(EcamStatus::from_status(test))
         } else {
-            Err(EcamError::Unknown)
+            Err(EcamError::Disconnected)
         };
         drop(status_interest);
         ret
@@ -399,19 +471,135 @@ impl Ecam {
         self.write(EcamPacket::from_represenation(r)).await
     }
 
+    /// Sends a request and waits for the matching [`Response`] (see [`Request::matches_response`]), re-sending the
+    /// request if the response appears to have been lost. Returns [`EcamError::Timeout`] if no response arrives.
+    pub async fn request(&self, req: Request) -> Result<Response, EcamError> {
+        self.request_with_attempts(req, REQUEST_ATTEMPTS).await
+    }
+
+    /// Sends a request and waits for the matching [`Response`], sending the request at most `attempts` times. Requests
+    /// that are not safe to repeat (ie: those that start a beverage) should use a single attempt.
+    pub async fn request_with_attempts(
+        &self,
+        req: Request,
+        attempts: usize,
+    ) -> Result<Response, EcamError> {
+        // Subscribe before writing so we can't miss the response
+        let mut tap = self.packet_tap().await?;
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                trace_packet!("Retrying {:?} (attempt {})", req, attempt + 1);
+            }
+            self.write_request(req.clone()).await?;
+            let deadline = tokio::time::Instant::now() + REQUEST_TIMEOUT;
+            loop {
+                match tokio::time::timeout_at(deadline, tap.next()).await {
+                    Err(_) => break,
+                    Ok(None) | Ok(Some(EcamOutput::Done)) => return Err(EcamError::Disconnected),
+                    Ok(Some(EcamOutput::Packet(EcamPacket {
+                        representation: Ok(packet),
+                        ..
+                    }))) => {
+                        if req.matches_response(&packet) {
+                            return Ok(packet);
+                        }
+                    }
+                    Ok(Some(EcamOutput::Packet(EcamPacket {
+                        representation: Err(e),
+                        bytes,
+                    }))) => {
+                        if bytes.bytes.first() == Some(&(req.ecam_request_id() as u8)) {
+                            return Err(EcamError::DecodeFailure(e, bytes.bytes));
+                        }
+                    }
+                    Ok(Some(EcamOutput::Ready)) => {}
+                }
+            }
+        }
+        Err(EcamError::Timeout)
+    }
+
     pub async fn packet_tap(&self) -> Result<impl Stream<Item = EcamOutput> + use<>, EcamError> {
         let internals = self.internals.lock().await;
         Ok(BroadcastStream::new(internals.packet_tap.subscribe())
             .map(|x| x.expect("Unexpected receive error")))
     }
 
+    /// Returns a stream of monitor responses, yielding the most recent response first and then each subsequent one that
+    /// differs from the last. The device is polled for status for as long as the stream is alive.
+    pub async fn status_stream(
+        &self,
+    ) -> Result<impl Stream<Item = MonitorStatus> + use<>, EcamError> {
+        let mut internals = self.internals.lock().await;
+        let status_interest = internals.status_interest.lock();
+        let rx = internals.last_status.clone();
+        drop(internals);
+        let mut last = None;
+        Ok(WatchStream::new(rx).filter_map(move |status| {
+            // Holding the interest handle in this closure keeps the monitor loop alive
+            let _ = &status_interest;
+            if status.is_none() || status == last {
+                None
+            } else {
+                last.clone_from(&status);
+                status
+            }
+        }))
+    }
+
+    /// Determines which monitor version the machine speaks by sending each monitor request in turn (newest first) and
+    /// waiting for any monitor response. Only [`MonitorVersion::V2`] is sent unless legacy probing was requested. Falls
+    /// back to [`MonitorVersion::V2`] if the machine does not answer.
+    async fn probe_monitor_version(
+        driver: &Arc<Box<dyn EcamDriver>>,
+        internals: &Arc<Mutex<EcamInternals>>,
+    ) -> MonitorVersion {
+        let (tap, probe_legacy_monitors) = {
+            let internals = internals.lock().await;
+            (
+                internals.packet_tap.subscribe(),
+                internals.probe_legacy_monitors,
+            )
+        };
+        let mut tap = BroadcastStream::new(tap);
+        let versions: &[MonitorVersion] = if probe_legacy_monitors {
+            &MonitorVersion::PROBE_ORDER
+        } else {
+            &[MonitorVersion::V2]
+        };
+        for &version in versions {
+            for _ in 0..REQUEST_ATTEMPTS {
+                let request = EcamDriverPacket::from_vec(version.request().encode());
+                if driver.write(request).await.is_err() {
+                    continue;
+                }
+                let deadline = tokio::time::Instant::now() + REQUEST_TIMEOUT;
+                while let Ok(Some(Ok(output))) = tokio::time::timeout_at(deadline, tap.next()).await
+                {
+                    if let Some(status) = output
+                        .take_packet()
+                        .as_ref()
+                        .and_then(MonitorStatus::from_response)
+                        && let Some(version) = status.version
+                    {
+                        trace_packet!("Machine speaks monitor {:?}", version);
+                        return version;
+                    }
+                }
+            }
+        }
+        warning!("Machine did not answer any monitor request, assuming V2");
+        MonitorVersion::V2
+    }
+
     /// The monitor loop is booted when the underlying driver reports that it is ready.
     async fn write_monitor_loop(
         driver: Arc<Box<dyn EcamDriver>>,
         internals: Arc<Mutex<EcamInternals>>,
         alive: Alive,
     ) -> Result<(), EcamError> {
-        let status_request = EcamDriverPacket::from_vec(Request::MonitorV2().encode());
+        let version = Self::probe_monitor_version(&driver, &internals).await;
+        let status_request = EcamDriverPacket::from_vec(version.request().encode());
         while alive.is_alive() {
             // Only send status update packets while there is status interest
             if internals.lock().await.status_interest.count() == 0 {
@@ -468,4 +656,25 @@ mod test {
             assert_eq!(status, expected_status);
         }
     }
+
+    #[tokio::test]
+    async fn wait_for_state_timeout() {
+        let driver =
+            crate::ecam::get_ecam_simulator(&crate::ecam::EcamId::Simulator("sim[on]".to_owned()))
+                .await
+                .expect("Failed to create simulator");
+        let ecam = Ecam::new(Box::new(driver), false).await;
+        ecam.wait_for_state_with_timeout(EcamStatus::Ready, |_| {}, Duration::from_secs(5))
+            .await
+            .expect("Expected the simulator to be ready");
+        assert!(matches!(
+            ecam.wait_for_state_with_timeout(
+                EcamStatus::StandBy,
+                |_| {},
+                Duration::from_millis(500)
+            )
+            .await,
+            Err(EcamError::Timeout)
+        ));
+    }
 }
@@ -2,7 +2,9 @@
 
 use std::fmt::Display;
 
+use crate::operations::IngredientCheckError;
 use crate::prelude::*;
+use crate::protocol::{DecodeError, Response};
 
 use thiserror::Error;
 
@@ -67,6 +69,16 @@ pub async fn ecam_lookup(id: &EcamId, dump_packets: bool) -> Result<Ecam, EcamEr
     Ok(Ecam::new(driver, dump_packets).await)
 }
 
+/// Like [`ecam_lookup`], but also probes the older monitor versions (see [`Ecam::new_probing_legacy_monitors`]).
+pub async fn ecam_lookup_probing_legacy_monitors(
+    id: &EcamId,
+    dump_packets: bool,
+) -> Result<Ecam, EcamError> {
+    let driver = Box::new(get_ecam_subprocess(id).await?);
+    trace_packet!("Got ECAM subprocess");
+    Ok(Ecam::new_probing_legacy_monitors(driver, dump_packets).await)
+}
+
 #[derive(Error, Debug)]
 pub enum EcamError {
     #[error("not found")]
@@ -75,6 +87,29 @@ pub enum EcamError {
     BTError(#[from] btleplug::Error),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
-    #[error("Unknown error")]
-    Unknown,
+    #[error("timed out")]
+    Timeout,
+    #[error("disconnected from device")]
+    Disconnected,
+    #[error("unexpected response: {0:?}")]
+    UnexpectedResponse(Box<Response>),
+    #[error("{0} (packet {1:02x?})")]
+    DecodeFailure(DecodeError, Vec<u8>),
+    #[error("machine is not ready: {0:?}")]
+    NotReady(EcamStatus),
+    #[error("ingredients failed validation: {0}")]
+    IngredientValidation(IngredientCheckError),
+    #[error("{0}")]
+    InvalidArgument(String),
+}
+
+impl EcamError {
+    /// Is this error likely to go away if the operation is retried later (ie: the machine was busy or did not respond),
+    /// as opposed to an error in the request itself?
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            EcamError::Timeout | EcamError::Disconnected | EcamError::NotReady(_)
+        )
+    }
 }
@@ -34,6 +34,17 @@
 //! Brewing RegularCoffee...
 //! ```
 //!
+//! Serve an HTTP API for the device, keeping the connection open between requests:
+//!
+//! ```console
+//! $ longshot serve --device-name (device) --bind 127.0.0.1:8080
+//! Listening on http://127.0.0.1:8080
+//! $ curl -s http://127.0.0.1:8080/status
+//! {"status":"ready"}
+//! $ curl -s -X POST http://127.0.0.1:8080/brew -H 'content-type: application/json' \
+//!     -d '{"beverage": "regularcoffee", "coffee": 180, "taste": "strong", "allow-defaults": true}'
+//! ```
+//!
 //! # API Examples
 //!
 //! Brew a long coffee with 250 impulses of water (approximately the size of an average North American coffee mug, or slightly more).
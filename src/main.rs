@@ -1,13 +1,15 @@
 #![warn(clippy::all)]
-use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
 use clap::{Arg, ArgMatches, arg, command};
+use std::time::Duration;
 
 mod app;
 
 embed_plist::embed_info_plist!("Info.plist");
 
 use longshot::ecam::{
-    Ecam, EcamBT, EcamError, EcamId, ecam_lookup, ecam_scan, get_ecam_simulator, pipe_stdin,
+    Ecam, EcamBT, EcamError, EcamId, ecam_lookup, ecam_lookup_probing_legacy_monitors, ecam_scan,
+    get_ecam_simulator, pipe_stdin,
 };
 use longshot::{operations::*, protocol::*};
 
@@ -15,23 +17,62 @@ fn enum_value_parser<T: MachineEnumerable<T> + 'static>() -> PossibleValuesParse
     PossibleValuesParser::new(T::all().map(|x| PossibleValue::new(x.to_arg_string())))
 }
 
+/// Parses a beverage name, listing the known beverages as possible values. Unknown names are accepted if they could be
+/// the name of a custom recipe, which is resolved once connected to the device.
+#[derive(Clone)]
+struct BeverageValueParser;
+
+impl TypedValueParser for BeverageValueParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<String, clap::Error> {
+        if let Some(name) = value.to_str()
+            && (EcamBeverageId::lookup_by_name_case_insensitive(name).is_some()
+                || name.chars().count() <= CUSTOM_RECIPE_NAME_LENGTH)
+        {
+            return Ok(name.to_owned());
+        }
+        // Too long to be a custom recipe, so report it as an invalid beverage (with suggestions)
+        enum_value_parser::<EcamBeverageId>().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            EcamBeverageId::all().map(|x| PossibleValue::new(x.to_arg_string())),
+        ))
+    }
+}
+
 struct DeviceCommon {
     device_id: EcamId,
     dump_packets: bool,
+    legacy_monitor: bool,
     turn_on: bool,
+    turn_on_timeout: Duration,
     allow_off: bool,
 }
 
 impl DeviceCommon {
-    fn args() -> [Arg; 4] {
+    fn args() -> [Arg; 6] {
         [
             arg!(--"device-name" <name>)
                 .help("Provides the name of the device")
                 .required(true),
             arg!(--"dump-packets").help("Dumps decoded packets to the terminal for debugging"),
+            arg!(--"legacy-monitor").help(
+                "Also probe V0/V1 monitor requests (layouts inferred from V2) if V2 gets no answer",
+            ),
             arg!(--"turn-on")
                 .help("Turn on the machine before running this operation")
                 .conflicts_with("allow-off"),
+            arg!(--"turn-on-timeout" <seconds>)
+                .help("How long to wait for the machine to turn on (defaults to 120 seconds)")
+                .value_parser(clap::value_parser!(u64)),
             arg!(--"allow-off")
                 .hide(true)
                 .help("Allow brewing while machine is off")
@@ -46,7 +87,9 @@ impl DeviceCommon {
                 .expect("Device name required")
                 .into(),
             dump_packets: cmd.get_flag("dump-packets"),
+            legacy_monitor: cmd.get_flag("legacy-monitor"),
             turn_on: cmd.get_flag("turn-on"),
+            turn_on_timeout: seconds(cmd, "turn-on-timeout").unwrap_or(DEFAULT_POWER_ON_TIMEOUT),
             allow_off: cmd.get_flag("allow-off"),
         }
     }
@@ -54,75 +97,266 @@ impl DeviceCommon {
 
 async fn ecam(cmd: &ArgMatches, allow_off_and_alarms: bool) -> Result<Ecam, EcamError> {
     let device_common = DeviceCommon::parse(cmd);
-    let ecam = ecam_lookup(&device_common.device_id, device_common.dump_packets).await?;
-    if !power_on(
+    let ecam = if device_common.legacy_monitor {
+        ecam_lookup_probing_legacy_monitors(&device_common.device_id, device_common.dump_packets)
+            .await?
+    } else {
+        ecam_lookup(&device_common.device_id, device_common.dump_packets).await?
+    };
+    power_on(
         ecam.clone(),
         device_common.allow_off | allow_off_and_alarms,
         allow_off_and_alarms,
         device_common.turn_on,
+        device_common.turn_on_timeout,
     )
-    .await?
-    {
-        longshot::display::shutdown();
-        std::process::exit(1);
-    }
+    .await?;
     Ok(ecam)
 }
 
+/// Parses an optional argument as a number of seconds.
+fn seconds(cmd: &ArgMatches, arg: &str) -> Option<Duration> {
+    cmd.get_one::<u64>(arg).copied().map(Duration::from_secs)
+}
+
+/// Parses a `YYYY-MM-DD` date as midnight, local time.
+fn parse_date(s: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| {
+            d.and_time(chrono::NaiveTime::MIN)
+                .and_local_timezone(chrono::Local)
+                .earliest()
+        })
+        .map(|d| d.to_utc())
+        .ok_or_else(|| format!("Invalid date '{}', expected YYYY-MM-DD", s))
+}
+
+fn stats_file_arg() -> Arg {
+    arg!(--"file" <path>)
+        .help("The statistics history file (defaults to one in the user data directory)")
+        .value_parser(clap::value_parser!(std::path::PathBuf))
+}
+
+/// Returns the statistics history file from [`stats_file_arg`], or the default.
+fn stats_file(cmd: &ArgMatches) -> Result<std::path::PathBuf, EcamError> {
+    cmd.get_one::<std::path::PathBuf>("file")
+        .cloned()
+        .or_else(default_stats_history_path)
+        .ok_or_else(|| {
+            EcamError::InvalidArgument("No user data directory, pass --file instead".to_owned())
+        })
+}
+
+fn restore_profile_arg() -> Arg {
+    arg!(--"restore-profile" <profile>)
+        .help("The active profile, which is selected again afterwards (required with --profile)")
+}
+
+/// Resolves the profile from [`restore_profile_arg`]. The active profile can't be read from the machine, so it must be
+/// given whenever `--profile` is, and otherwise `profile` is assumed to be active and is left selected.
+async fn restore_profile(cmd: &ArgMatches, ecam: &Ecam, profile: u8) -> Result<u8, EcamError> {
+    match cmd.get_one::<String>("restore-profile") {
+        Some(restore) => resolve_profile(ecam.clone(), restore).await,
+        None if cmd.contains_id("profile") => Err(EcamError::InvalidArgument(
+            "--restore-profile is required with --profile, as the active profile can't be read"
+                .to_owned(),
+        )),
+        None => Ok(profile),
+    }
+}
+
+fn profile_arg() -> Arg {
+    arg!(--"profile" <profile>).help("The profile number or name to use (defaults to profile 1)")
+}
+
+/// Resolves the optional `--profile` argument to a profile number.
+async fn profile(cmd: &ArgMatches, ecam: &Ecam) -> Result<u8, EcamError> {
+    match cmd.get_one::<String>("profile") {
+        Some(profile) => resolve_profile(ecam.clone(), profile).await,
+        None => Ok(DEFAULT_PROFILE),
+    }
+}
+
+/// Arguments shared by commands that take a beverage and its ingredients.
+fn brew_args() -> Vec<Arg> {
+    vec![
+        arg!(--"beverage" <name>)
+            .required(true)
+            .help("The beverage to brew, or the name of a custom recipe")
+            .value_parser(BeverageValueParser),
+        profile_arg(),
+        arg!(--"coffee" <amount>)
+            .help("Amount of coffee to brew")
+            .value_parser(0..=2500),
+        arg!(--"milk" <amount>)
+            .help("Amount of milk to steam/pour")
+            .value_parser(0..=2500),
+        arg!(--"hotwater" <amount>)
+            .help("Amount of hot water to pour")
+            .value_parser(0..=2500),
+        arg!(--"taste" <taste>)
+            .help("The strength of the beverage")
+            .value_parser(enum_value_parser::<EcamBeverageTaste>()),
+        arg!(--"temperature" <temperature>)
+            .help("The temperature of the beverage")
+            .value_parser(enum_value_parser::<EcamTemperature>()),
+        arg!(--"allow-defaults").help("Allow brewing if some parameters are not specified"),
+        arg!(--"force").help("Allow brewing with parameters that do not validate"),
+        arg!(--"timeout" <seconds>)
+            .help("How long to wait for the beverage to finish brewing (defaults to 300 seconds)")
+            .value_parser(clap::value_parser!(u64)),
+    ]
+}
+
+/// Parses the arguments from [`brew_args`], returning `None` if an ingredient is invalid. The beverage is returned
+/// unresolved, as custom recipe names must be looked up on the device.
+fn parse_brew_args(
+    cmd: &ArgMatches,
+) -> Option<(String, Vec<BrewIngredientInfo>, IngredientCheckMode)> {
+    let allow_defaults = cmd.get_flag("allow-defaults");
+    let force = cmd.get_flag("force");
+
+    let beverage = cmd.get_one::<String>("beverage").unwrap().clone();
+
+    let mut ingredients = vec![];
+    for arg in ["coffee", "milk", "hotwater", "taste", "temperature"] {
+        if let Some(value) = cmd.get_raw(arg) {
+            // Once clap has had a chance to validate the args, we go back to the underlying OsStr to parse it
+            let value = value.into_iter().next().unwrap().to_str().unwrap();
+            if let Some(ingredient) = BrewIngredientInfo::from_arg(arg, value) {
+                ingredients.push(ingredient);
+            } else {
+                eprintln!("Invalid value '{}' for argument '{}'", value, arg);
+                return None;
+            }
+        }
+    }
+
+    let mode = match (allow_defaults, force) {
+        (_, true) => IngredientCheckMode::Force,
+        (true, false) => IngredientCheckMode::AllowDefaults,
+        (false, false) => IngredientCheckMode::Strict,
+    };
+    Some((beverage, ingredients, mode))
+}
+
 fn command() -> clap::Command {
     command!()
         .arg(arg!(--"trace").help("Trace packets to/from device"))
         .subcommand(
             command!("brew")
-                .about("Brew a coffee")
+                .about("Brew a coffee (press Ctrl+C to stop brewing)")
+                .args(DeviceCommon::args())
+                .args(brew_args())
+                .arg(
+                    arg!(--"skip-brew")
+                        .hide(true)
+                        .help("Does everything except actually brew the beverage"),
+                ),
+        )
+        .subcommand(
+            command!("stop")
+                .about("Stop a beverage that is being brewed")
                 .args(DeviceCommon::args())
                 .arg(
                     arg!(--"beverage" <name>)
                         .required(true)
-                        .help("The beverage to brew")
+                        .help("The beverage being brewed")
                         .value_parser(enum_value_parser::<EcamBeverageId>()),
                 )
                 .arg(
-                    arg!(--"coffee" <amount>)
-                        .help("Amount of coffee to brew")
-                        .value_parser(0..=2500),
-                )
-                .arg(
-                    arg!(--"milk" <amount>)
-                        .help("Amount of milk to steam/pour")
-                        .value_parser(0..=2500),
-                )
-                .arg(
-                    arg!(--"hotwater" <amount>)
-                        .help("Amount of hot water to pour")
-                        .value_parser(0..=2500),
-                )
-                .arg(
-                    arg!(--"taste" <taste>)
-                        .help("The strength of the beverage")
-                        .value_parser(enum_value_parser::<EcamBeverageTaste>()),
-                )
-                .arg(
-                    arg!(--"temperature" <temperature>)
-                        .help("The temperature of the beverage")
-                        .value_parser(enum_value_parser::<EcamTemperature>()),
-                )
+                    arg!(--"timeout" <seconds>)
+                        .help("How long to wait for the beverage to stop (defaults to 60 seconds)")
+                        .value_parser(clap::value_parser!(u64)),
+                ),
+        )
+        .subcommand(
+            command!("save-recipe")
+                .about("Save a recipe as the default for a beverage")
+                .args(DeviceCommon::args())
+                .args(brew_args())
+                .arg(restore_profile_arg())
+                .arg(arg!(--"brew").help("Brew the beverage while saving it")),
+        )
+        .subcommand(
+            command!("reset-recipe")
+                .about("Reset a beverage to the factory default recipe")
+                .args(DeviceCommon::args())
                 .arg(
-                    arg!(--"allow-defaults")
-                        .help("Allow brewing if some parameters are not specified"),
+                    arg!(--"beverage" <name>)
+                        .required(true)
+                        .help("The beverage to reset")
+                        .value_parser(enum_value_parser::<EcamBeverageId>()),
                 )
-                .arg(arg!(--"force").help("Allow brewing with parameters that do not validate"))
-                .arg(
-                    arg!(--"skip-brew")
-                        .hide(true)
-                        .help("Does everything except actually brew the beverage"),
-                ),
+                .arg(profile_arg())
+                .arg(restore_profile_arg()),
         )
         .subcommand(
             command!("monitor")
                 .about("Monitor the status of the device")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"diff-unknown").help(
+                        "Write a CSV row to stdout whenever the undecoded status fields change",
+                    ),
+                ),
+        )
+        .subcommand(
+            command!("favorites")
+                .about("Change the favorite beverages for a profile (`priority get` shows them)")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("set")
+                        .about("Set the favorite beverages for a profile, in display order")
+                        .args(DeviceCommon::args())
+                        .arg(profile_arg())
+                        .arg(
+                            arg!(--"beverage" <name>)
+                                .required(true)
+                                .num_args(1..)
+                                .help("The favorite beverages")
+                                .value_parser(enum_value_parser::<EcamBeverageId>()),
+                        ),
+                ),
+        )
+        .subcommand(
+            command!("priority")
+                .about("Show the order of beverages on the display for a profile")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("get")
+                        .about("Show the order of beverages on the display for a profile")
+                        .args(DeviceCommon::args())
+                        .arg(profile_arg())
+                        .arg(restore_profile_arg()),
+                ),
+        )
+        .subcommand(
+            command!("pin")
+                .about("Check the PIN lock")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("status")
+                        .about("Show whether the PIN lock is enabled (reply layout is inferred)")
+                        .args(DeviceCommon::args()),
+                ),
+        )
+        .subcommand(
+            command!("list-beans")
+                .about("List the bean systems stored on the device")
                 .args(DeviceCommon::args()),
         )
+        .subcommand(
+            command!("select-bean")
+                .about("Select the active bean system")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"bean" <bean>)
+                        .required(true)
+                        .help("The bean number or name to select"),
+                ),
+        )
         .subcommand(
             command!("status")
                 .about("Print the status of the device and then exit")
@@ -143,6 +377,31 @@ fn command() -> clap::Command {
                         .help("The parameter length"),
                 ),
         )
+        .subcommand(
+            command!("write-parameter")
+                .about("Write a parameter to the device (potentially dangerous)")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"parameter" <parameter>)
+                        .required(true)
+                        .help("The parameter ID"),
+                )
+                .arg(
+                    arg!(--"data" <data>)
+                        .required(true)
+                        .help("The data to write, in hex"),
+                )
+                .arg(
+                    arg!(--"force").help(
+                        "Allow writing parameters (no parameter is known to be safe to write)",
+                    ),
+                )
+                .arg(
+                    arg!(--"dry-run").help(
+                        "Print the packet that would be sent without connecting to the device",
+                    ),
+                ),
+        )
         .subcommand(
             command!("read-statistic")
                 .about("Read a statistic from the device")
@@ -163,18 +422,146 @@ fn command() -> clap::Command {
                 .about("Read all statistics from the device")
                 .args(DeviceCommon::args()),
         )
+        .subcommand(
+            command!("watch-maintenance")
+                .about("Follow a maintenance cycle started from the machine's front panel")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(<cycle> "The maintenance cycle").value_parser(PossibleValuesParser::new(
+                        MaintenanceCycle::ALL.map(|c| PossibleValue::new(c.name())),
+                    )),
+                )
+                .arg(
+                    arg!(--"timeout" <seconds>)
+                        .help("How long to wait for the cycle (defaults depend on the cycle)")
+                        .value_parser(clap::value_parser!(u64)),
+                ),
+        )
+        .subcommand(
+            command!("stats")
+                .about("Record statistics snapshots and show usage between them")
+                .subcommand_required(true)
+                .subcommand(
+                    command!("snapshot")
+                        .about("Read all statistics from the device and append them to the history")
+                        .args(DeviceCommon::args())
+                        .arg(stats_file_arg()),
+                )
+                .subcommand(
+                    command!("diff")
+                        .about("Show what changed between the snapshots in the history")
+                        .arg(stats_file_arg())
+                        .arg(
+                            arg!(--"since" <date>)
+                                .help("Start at the first snapshot from this date (YYYY-MM-DD)")
+                                .value_parser(parse_date),
+                        )
+                        .arg(
+                            arg!(--"until" <date>)
+                                .help("End at the last snapshot before this date (YYYY-MM-DD)")
+                                .value_parser(parse_date),
+                        )
+                        .arg(
+                            arg!(--"descale-statistic" <id>)
+                                .help("Statistic ID counting descales, to show the last descale")
+                                .value_parser(clap::value_parser!(u16)),
+                        ),
+                ),
+        )
         .subcommand(
             command!("read-parameter-memory")
                 .about("Read the parameter memory from the device")
-                .args(DeviceCommon::args()),
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"output" <path>)
+                        .help("Also write the parameter memory to a dump file for param-diff")
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                ),
+        )
+        .subcommand(
+            command!("param-diff")
+                .about("Compare two parameter dumps written by read-parameter-memory --output")
+                .arg(
+                    arg!(<before> "The earlier dump file")
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(
+                    arg!(<after> "The later dump file")
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                ),
         )
         .subcommand(
             command!("list-recipes")
                 .about("List recipes stored in the device")
                 .args(DeviceCommon::args())
+                .arg(profile_arg())
                 .arg(arg!(--"detail").help("Show detailed ingredient information"))
                 .arg(arg!(--"raw").help("Show raw ingredient information")),
         )
+        .subcommand(
+            command!("serve")
+                .about("Serve an HTTP API for the device")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"bind" <address>)
+                        .help("The address to listen on")
+                        .default_value("127.0.0.1:8080")
+                        .value_parser(clap::value_parser!(std::net::SocketAddr)),
+                ),
+        )
+        .subcommand(
+            command!("machine-info")
+                .about("Show the checksum, beverages and raw parameters of unknown meaning")
+                .args(DeviceCommon::args())
+                .arg(arg!(--"json").help("Print the information as JSON")),
+        )
+        .subcommand(
+            command!("list-profiles")
+                .about("List user profiles stored in the device")
+                .args(DeviceCommon::args()),
+        )
+        .subcommand(
+            command!("rename-profile")
+                .about("Change the name and/or icon of a user profile")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"profile" <profile>)
+                        .required(true)
+                        .help("The profile number or name"),
+                )
+                .arg(arg!(--"name" <name>).help("The new name (up to ten characters)"))
+                .arg(
+                    arg!(--"icon" <icon>)
+                        .help("The new icon number")
+                        .value_parser(clap::value_parser!(u8)),
+                ),
+        )
+        .subcommand(
+            command!("rename-recipe")
+                .about("Change the name and/or icon of a custom recipe")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"beverage" <name>)
+                        .required(true)
+                        .help("The custom recipe (custom01 to custom10) or its current name"),
+                )
+                .arg(arg!(--"name" <name>).help("The new name (up to ten characters)"))
+                .arg(
+                    arg!(--"icon" <icon>)
+                        .help("The new icon number")
+                        .value_parser(clap::value_parser!(u8)),
+                ),
+        )
+        .subcommand(
+            command!("select-profile")
+                .about("Select the active user profile")
+                .args(DeviceCommon::args())
+                .arg(
+                    arg!(--"profile" <profile>)
+                        .required(true)
+                        .help("The profile number or name"),
+                ),
+        )
         .subcommand(command!("list").about("List all supported devices"))
         .subcommand(
             command!("x-internal-pipe")
@@ -205,45 +592,113 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match subcommand {
         Some(("brew", cmd)) => {
             let skip_brew = cmd.get_flag("skip-brew");
-            let allow_defaults = cmd.get_flag("allow-defaults");
-            let force = cmd.get_flag("force");
-
-            let beverage: EcamBeverageId = EcamBeverageId::lookup_by_name_case_insensitive(
+            let timeout = seconds(cmd, "timeout").unwrap_or(DEFAULT_BREW_TIMEOUT);
+            let Some((beverage, ingredients, mode)) = parse_brew_args(cmd) else {
+                return Ok(());
+            };
+            let ecam = ecam(cmd, false).await?;
+            let beverage = resolve_beverage(ecam.clone(), &beverage).await?;
+            let profile = profile(cmd, &ecam).await?;
+            let recipe = validate_brew(ecam.clone(), profile, beverage, ingredients, mode).await?;
+            tokio::select! {
+                res = brew(ecam.clone(), skip_brew, beverage, recipe, timeout) => res?,
+                _ = tokio::signal::ctrl_c() => {
+                    cancel_brew(ecam, beverage, DEFAULT_CANCEL_TIMEOUT).await?
+                }
+            }
+        }
+        Some(("stop", cmd)) => {
+            let beverage = EcamBeverageId::lookup_by_name_case_insensitive(
                 cmd.get_one::<String>("beverage").unwrap(),
             )
             .expect("Beverage required");
-
-            let mut ingredients = vec![];
-            for arg in ["coffee", "milk", "hotwater", "taste", "temperature"] {
-                if let Some(value) = cmd.get_raw(arg) {
-                    // Once clap has had a chance to validate the args, we go back to the underlying OsStr to parse it
-                    let value = value.into_iter().next().unwrap().to_str().unwrap();
-                    if let Some(ingredient) = BrewIngredientInfo::from_arg(arg, value) {
-                        ingredients.push(ingredient);
-                    } else {
-                        eprintln!("Invalid value '{}' for argument '{}'", value, arg);
-                        return Ok(());
-                    }
-                }
-            }
-
-            let mode = match (allow_defaults, force) {
-                (_, true) => IngredientCheckMode::Force,
-                (true, false) => IngredientCheckMode::AllowDefaults,
-                (false, false) => IngredientCheckMode::Strict,
+            let timeout = seconds(cmd, "timeout").unwrap_or(DEFAULT_CANCEL_TIMEOUT);
+            let ecam = ecam(cmd, true).await?;
+            cancel_brew(ecam, beverage, timeout).await?;
+        }
+        Some(("save-recipe", cmd)) => {
+            let and_brew = cmd.get_flag("brew");
+            let timeout = seconds(cmd, "timeout").unwrap_or(DEFAULT_BREW_TIMEOUT);
+            let Some((beverage, ingredients, mode)) = parse_brew_args(cmd) else {
+                return Ok(());
             };
-            let ecam = ecam(cmd, false).await?;
-            let recipe = validate_brew(ecam.clone(), beverage, ingredients, mode).await?;
-            brew(ecam.clone(), skip_brew, beverage, recipe).await?;
+            let ecam = ecam(cmd, !and_brew).await?;
+            let beverage = resolve_beverage(ecam.clone(), &beverage).await?;
+            let profile = profile(cmd, &ecam).await?;
+            let recipe = validate_brew(ecam.clone(), profile, beverage, ingredients, mode).await?;
+            let restore = restore_profile(cmd, &ecam, profile).await?;
+            save_recipe(ecam, profile, restore, beverage, recipe, and_brew, timeout).await?;
+        }
+        Some(("reset-recipe", cmd)) => {
+            let beverage = EcamBeverageId::lookup_by_name_case_insensitive(
+                cmd.get_one::<String>("beverage").unwrap(),
+            )
+            .expect("Beverage required");
+            let ecam = ecam(cmd, true).await?;
+            let profile = profile(cmd, &ecam).await?;
+            let restore = restore_profile(cmd, &ecam, profile).await?;
+            reset_recipe(ecam, profile, restore, beverage).await?;
         }
         Some(("monitor", cmd)) => {
             let ecam = ecam(cmd, true).await?;
-            monitor(ecam).await?;
+            if cmd.get_flag("diff-unknown") {
+                monitor_diff_unknown(ecam).await?;
+            } else {
+                monitor(ecam).await?;
+            }
+        }
+        Some(("favorites", cmd)) => match cmd.subcommand() {
+            Some(("set", cmd)) => {
+                let beverages = cmd
+                    .get_many::<String>("beverage")
+                    .unwrap()
+                    .map(|b| {
+                        EcamBeverageId::lookup_by_name_case_insensitive(b)
+                            .expect("Beverage required")
+                    })
+                    .collect::<Vec<_>>();
+                let ecam = ecam(cmd, true).await?;
+                let profile = profile(cmd, &ecam).await?;
+                set_favorite_beverages(ecam, profile, &beverages).await?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("priority", cmd)) => match cmd.subcommand() {
+            Some(("get", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                let profile = profile(cmd, &ecam).await?;
+                let restore = restore_profile(cmd, &ecam, profile).await?;
+                list_beverage_priority(ecam, profile, restore).await?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("pin", cmd)) => match cmd.subcommand() {
+            Some(("status", cmd)) => {
+                let ecam = ecam(cmd, true).await?;
+                show_pin_status(ecam).await?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("list-beans", cmd)) => {
+            let ecam = ecam(cmd, true).await?;
+            list_bean_systems(ecam).await?;
+        }
+        Some(("select-bean", cmd)) => {
+            let bean = cmd.get_one::<String>("bean").unwrap();
+            let ecam = ecam(cmd, true).await?;
+            select_bean(ecam, bean).await?;
         }
         Some(("status", cmd)) => {
             let ecam = ecam(cmd, true).await?;
             eprintln!("Status = {:?}", ecam.current_state().await?);
         }
+        Some(("serve", cmd)) => {
+            let addr = *cmd
+                .get_one::<std::net::SocketAddr>("bind")
+                .expect("Required");
+            let ecam = ecam(cmd, true).await?;
+            app::web::serve(ecam, addr).await?;
+        }
         Some(("list", _cmd)) => {
             let (s, uuid) = ecam_scan().await?;
             longshot::info!("{}  {}", s, uuid);
@@ -252,14 +707,45 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let ecam = ecam(cmd, true).await?;
             let detailed = cmd.get_flag("detail");
             let raw = cmd.get_flag("raw");
+            let profile = profile(cmd, &ecam).await?;
             if detailed {
-                list_recipes_detailed(ecam).await?;
+                list_recipes_detailed(ecam, profile).await?;
             } else if raw {
-                list_recipes_raw(ecam).await?;
+                list_recipes_raw(ecam, profile).await?;
             } else {
-                list_recipes(ecam).await?;
+                list_recipes(ecam, profile).await?;
             }
         }
+        Some(("machine-info", cmd)) => {
+            let json = cmd.get_flag("json");
+            let ecam = ecam(cmd, true).await?;
+            machine_info(ecam, json).await?;
+        }
+        Some(("list-profiles", cmd)) => {
+            let ecam = ecam(cmd, true).await?;
+            list_profiles(ecam).await?;
+        }
+        Some(("rename-profile", cmd)) => {
+            let profile = cmd.get_one::<String>("profile").expect("Required");
+            let name = cmd.get_one::<String>("name");
+            let icon = cmd.get_one::<u8>("icon").copied();
+            let ecam = ecam(cmd, true).await?;
+            rename_profile(ecam, profile, name.map(String::as_str), icon).await?;
+        }
+        Some(("rename-recipe", cmd)) => {
+            let beverage = cmd.get_one::<String>("beverage").expect("Required");
+            let name = cmd.get_one::<String>("name");
+            let icon = cmd.get_one::<u8>("icon").copied();
+            let ecam = ecam(cmd, true).await?;
+            rename_custom_recipe(ecam, beverage, name.map(String::as_str), icon).await?;
+        }
+        Some(("select-profile", cmd)) => {
+            let profile = cmd.get_one::<String>("profile").expect("Required");
+            let ecam = ecam(cmd, true).await?;
+            let profile = find_profile(ecam.clone(), profile).await?;
+            select_profile(ecam, profile.id).await?;
+            longshot::info!("Selected profile {} ({})", profile.id, profile.name);
+        }
         Some(("read-parameter", cmd)) => {
             let parameter = cmd
                 .get_one::<String>("parameter")
@@ -272,9 +758,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let ecam = ecam(cmd, true).await?;
             read_parameter(ecam, parameter, length).await?;
         }
+        Some(("write-parameter", cmd)) => {
+            let parameter = cmd
+                .get_one::<String>("parameter")
+                .map(|s| s.parse::<u16>().expect("Invalid number"))
+                .expect("Required");
+            let data = cmd
+                .get_one::<String>("data")
+                .map(|s| hex::decode(s).expect("Invalid hex data"))
+                .expect("Required");
+            if cmd.get_flag("dry-run") {
+                write_parameter_dry_run(parameter, data);
+            } else {
+                let ecam = ecam(cmd, true).await?;
+                write_parameter(ecam, parameter, data, cmd.get_flag("force")).await?;
+            }
+        }
+        Some(("watch-maintenance", cmd)) => {
+            let cycle = MaintenanceCycle::lookup(cmd.get_one::<String>("cycle").expect("Required"))
+                .expect("Cycle required");
+            let timeout = seconds(cmd, "timeout").unwrap_or(cycle.default_timeout());
+            let ecam = ecam(cmd, true).await?;
+            for step in cycle.instructions() {
+                longshot::info!("Before starting: {}", step);
+            }
+            watch_maintenance(ecam, cycle, timeout).await?;
+        }
+        Some(("stats", cmd)) => match cmd.subcommand() {
+            Some(("snapshot", cmd)) => {
+                let path = stats_file(cmd)?;
+                let ecam = ecam(cmd, true).await?;
+                record_stats_snapshot(ecam, &path).await?;
+            }
+            Some(("diff", cmd)) => {
+                let path = stats_file(cmd)?;
+                let since = cmd.get_one("since").copied();
+                let until = cmd.get_one("until").copied();
+                let descale_stat = cmd.get_one::<u16>("descale-statistic").copied();
+                show_stats_diff(&path, since, until, descale_stat)?;
+            }
+            _ => unreachable!(),
+        },
         Some(("read-statistics", cmd)) => {
             let ecam = ecam(cmd, true).await?;
-            read_statistics(ecam).await?;
+            list_statistics(ecam).await?;
         }
         Some(("read-statistic", cmd)) => {
             let parameter = cmd
@@ -289,8 +816,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             read_statistic(ecam, parameter, length).await?;
         }
         Some(("read-parameter-memory", cmd)) => {
+            let output = cmd.get_one::<std::path::PathBuf>("output");
             let ecam = ecam(cmd, true).await?;
-            read_parameter_memory(ecam).await?;
+            dump_parameter_memory(ecam, output.map(|p| p.as_path())).await?;
+        }
+        Some(("param-diff", cmd)) => {
+            let before = cmd
+                .get_one::<std::path::PathBuf>("before")
+                .expect("Required");
+            let after = cmd
+                .get_one::<std::path::PathBuf>("after")
+                .expect("Required");
+            param_diff(before, after)?;
         }
         Some(("app-control", cmd)) => {
             let ecam = ecam(cmd, true).await?;
@@ -0,0 +1,89 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::*,
+};
+
+/// Reads all of the bean systems from the device, returning them with their 1-based bean numbers. The number of bean
+/// systems isn't known, so they are read one at a time until the machine returns no entry or stops replying.
+pub async fn read_bean_systems(ecam: Ecam) -> Result<Vec<(u8, BeanSystem)>, EcamError> {
+    let mut beans = vec![];
+    for bean in 1..=u8::MAX {
+        match ecam.request(Request::BeanSystemRead(bean, bean)).await {
+            Ok(Response::BeanSystemRead(read)) => match read.into_iter().next() {
+                Some(system) => beans.push((bean, system)),
+                None => break,
+            },
+            Ok(packet) => return Err(EcamError::UnexpectedResponse(Box::new(packet))),
+            Err(EcamError::Timeout) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(beans)
+}
+
+/// Resolves a bean number or case-insensitive bean name to a bean number, checking it against the bean systems on the
+/// device.
+pub async fn resolve_bean_system(ecam: Ecam, bean: &str) -> Result<u8, EcamError> {
+    let beans = read_bean_systems(ecam).await?;
+    match bean.parse::<u8>() {
+        Ok(id) if beans.iter().any(|(i, _)| *i == id) => Ok(id),
+        Ok(id) => Err(EcamError::InvalidArgument(format!(
+            "Bean {} is out of range (1-{})",
+            id,
+            beans.len()
+        ))),
+        Err(_) => beans
+            .into_iter()
+            .find(|(_, b)| b.name.eq_ignore_ascii_case(bean))
+            .map(|(id, _)| id)
+            .ok_or_else(|| {
+                info!("No bean found matching '{}'", bean);
+                EcamError::NotFound
+            }),
+    }
+}
+
+/// Selects the given bean number as the active bean system.
+pub async fn select_bean_system(ecam: Ecam, bean: u8) -> Result<(), EcamError> {
+    ecam.request(Request::BeanSystemSelect(bean)).await?;
+    Ok(())
+}
+
+pub async fn list_bean_systems(ecam: Ecam) -> Result<(), EcamError> {
+    for (id, bean) in read_bean_systems(ecam).await? {
+        info!(
+            "{}: {} (temperature {:?}, taste {:?}, grind {})",
+            id, bean.name, bean.temperature, bean.taste, bean.grind
+        );
+    }
+    Ok(())
+}
+
+/// Selects the bean system matching the given number or name.
+pub async fn select_bean(ecam: Ecam, bean: &str) -> Result<(), EcamError> {
+    let id = resolve_bean_system(ecam.clone(), bean).await?;
+    select_bean_system(ecam, id).await?;
+    info!("Selected bean {}", id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_simulator() {
+        let driver =
+            crate::ecam::get_ecam_simulator(&crate::ecam::EcamId::Simulator("sim[on]".to_owned()))
+                .await
+                .expect("Failed to create simulator");
+        let ecam = Ecam::new(Box::new(driver), false).await;
+        let beans = read_bean_systems(ecam).await.expect("Failed to read");
+        assert_eq!(
+            beans.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            (1..=7).collect::<Vec<_>>()
+        );
+        assert_eq!(beans[6].1.name, "BEAN 7");
+    }
+}
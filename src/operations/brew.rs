@@ -2,40 +2,38 @@ use crate::{display, prelude::*};
 use crate::{
     ecam::{Ecam, EcamError, EcamStatus},
     operations::{
-        BrewIngredientInfo, IngredientCheckError, IngredientCheckMode, check_ingredients,
-        list_recipies_for,
+        BrewIngredientInfo, IngredientCheckMode, check_ingredients, list_recipies_for,
+        with_profile_selected,
     },
     protocol::*,
 };
 
-/// Checks the arguments for the given beverage against the machine's recipes and returns a computed recipe.
+/// Checks the arguments for the given beverage against the machine's recipes for the given profile and returns a
+/// computed recipe.
 pub async fn validate_brew(
     ecam: Ecam,
+    profile: u8,
     beverage: EcamBeverageId,
     ingredients: Vec<BrewIngredientInfo>,
     mode: IngredientCheckMode,
 ) -> Result<Vec<RecipeInfo<u16>>, EcamError> {
     info!("Fetching recipe for {:?}...", beverage);
-    let recipe_list = list_recipies_for(ecam.clone(), Some(vec![beverage])).await?;
+    let recipe_list = list_recipies_for(ecam.clone(), profile, Some(vec![beverage])).await?;
     let recipe = recipe_list.find(beverage);
     if let Some(recipe) = recipe {
         let ranges = recipe.fetch_ingredients();
         match check_ingredients(mode, &ingredients, &ranges) {
-            Err(IngredientCheckError {
-                missing,
-                extra,
-                range_errors,
-            }) => {
-                for m in missing {
+            Err(err) => {
+                for m in &err.missing {
                     info!("{}", m.to_arg_string().unwrap_or(format!("{:?}", m)));
                 }
-                for e in extra {
+                for e in &err.extra {
                     info!("{}", e.to_arg_string());
                 }
-                for r in range_errors {
+                for r in &err.range_errors {
                     info!("{}", r.1);
                 }
-                Err(EcamError::Unknown)
+                Err(EcamError::IngredientValidation(err))
             }
             Ok(result) => {
                 info!(
@@ -60,11 +58,16 @@ pub async fn validate_brew(
     }
 }
 
+/// The default amount of time to wait for a beverage to start and finish dispensing.
+pub const DEFAULT_BREW_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Brews the beverage with the given recipe, waiting up to `timeout` for it to start and finish dispensing.
 pub async fn brew(
     ecam: Ecam,
     skip_brew: bool,
     beverage: EcamBeverageId,
     recipe: Vec<RecipeInfo<u16>>,
+    timeout: Duration,
 ) -> Result<(), EcamError> {
     let req = Request::BeverageDispensingMode(
         beverage.into(),
@@ -79,14 +82,26 @@ pub async fn brew(
         ecam.write_request(req).await?;
     }
 
+    wait_for_brew(ecam, timeout).await
+}
+
+/// Waits for a beverage that has been started to finish dispensing, with the timeout covering the entire process.
+async fn wait_for_brew(ecam: Ecam, timeout: Duration) -> Result<(), EcamError> {
+    let deadline = tokio::time::Instant::now() + timeout;
+
     // Wait for not ready
-    ecam.wait_for_not_state(EcamStatus::Ready, display::display_status)
-        .await?;
+    ecam.wait_for_not_state_with_timeout(
+        EcamStatus::Ready,
+        display::display_status,
+        deadline - tokio::time::Instant::now(),
+    )
+    .await?;
 
     // Wait for not busy
-    ecam.wait_for(
-        |m| !matches!(EcamStatus::extract(m), EcamStatus::Busy(_)),
+    ecam.wait_for_with_timeout(
+        |m| !matches!(EcamStatus::from_status(m), EcamStatus::Busy(_)),
         display::display_status,
+        deadline - tokio::time::Instant::now(),
     )
     .await?;
 
@@ -94,3 +109,104 @@ pub async fn brew(
 
     Ok(())
 }
+
+/// The default amount of time to wait for a beverage to stop dispensing after it is cancelled.
+pub const DEFAULT_CANCEL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Stops the given beverage if it is being dispensed, and waits up to `timeout` for the machine to stop dispensing.
+pub async fn cancel_brew(
+    ecam: Ecam,
+    beverage: EcamBeverageId,
+    timeout: Duration,
+) -> Result<(), EcamError> {
+    info!("Stopping {:?}...", beverage);
+    let req = Request::BeverageDispensingMode(
+        beverage.into(),
+        EcamOperationTrigger::StartProgramOrStopV2.into(),
+        vec![],
+        EcamBeverageTasteType::Prepare.into(),
+    );
+    ecam.write_request(req).await?;
+
+    ecam.wait_for_with_timeout(
+        |m| !matches!(EcamStatus::from_status(m), EcamStatus::Busy(_)),
+        display::display_status,
+        timeout,
+    )
+    .await?;
+
+    display::log(display::LogLevel::Info, "Stopped");
+
+    Ok(())
+}
+
+/// Saves the recipe as the new default for the beverage in the given profile, optionally brewing it at the same time.
+///
+/// The machine saves recipes to the active profile, so the profile is selected before the recipe is saved, and the
+/// `restore` profile is selected again afterwards.
+pub async fn save_recipe(
+    ecam: Ecam,
+    profile: u8,
+    restore: u8,
+    beverage: EcamBeverageId,
+    recipe: Vec<RecipeInfo<u16>>,
+    and_brew: bool,
+    timeout: Duration,
+) -> Result<(), EcamError> {
+    with_profile_selected(
+        ecam.clone(),
+        profile,
+        restore,
+        save_recipe_to_active_profile(ecam, profile, beverage, recipe, and_brew, timeout),
+    )
+    .await
+}
+
+async fn save_recipe_to_active_profile(
+    ecam: Ecam,
+    profile: u8,
+    beverage: EcamBeverageId,
+    recipe: Vec<RecipeInfo<u16>>,
+    and_brew: bool,
+    timeout: Duration,
+) -> Result<(), EcamError> {
+    let (trigger, mode) = if and_brew {
+        (
+            EcamOperationTrigger::Start,
+            EcamBeverageTasteType::PrepareAndSave,
+        )
+    } else {
+        (EcamOperationTrigger::DontCare, EcamBeverageTasteType::Save)
+    };
+    let req = Request::BeverageDispensingMode(beverage.into(), trigger.into(), recipe, mode.into());
+    if and_brew {
+        // Don't risk brewing twice by re-sending the request
+        ecam.request_with_attempts(req, 1).await?;
+    } else {
+        ecam.request(req).await?;
+    }
+    info!("Saved {:?} for profile {}", beverage, profile);
+    if and_brew {
+        wait_for_brew(ecam, timeout).await?;
+    }
+    Ok(())
+}
+
+/// Resets the beverage in the given profile to the factory default recipe, selecting the `restore` profile again
+/// afterwards.
+pub async fn reset_recipe(
+    ecam: Ecam,
+    profile: u8,
+    restore: u8,
+    beverage: EcamBeverageId,
+) -> Result<(), EcamError> {
+    let req = Request::BeverageDispensingMode(
+        beverage.into(),
+        EcamOperationTrigger::DontCare.into(),
+        vec![],
+        EcamBeverageTasteType::Delete.into(),
+    );
+    with_profile_selected(ecam.clone(), profile, restore, ecam.request(req)).await?;
+    info!("Reset {:?} for profile {}", beverage, profile);
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    operations::with_profile_selected,
+    prelude::*,
+    protocol::*,
+};
+
+/// Formats a beverage from the priority list as an argument string, falling back to the raw value if it is unknown.
+fn beverage_to_string(beverage: MachineEnum<EcamBeverageId>) -> String {
+    match beverage {
+        MachineEnum::Value(beverage) => beverage.to_arg_string(),
+        MachineEnum::Unknown(value) => format!("unknown({})", value),
+    }
+}
+
+/// Reads the order of beverages on the machine's display for the given profile. The machine reports the order for the
+/// active profile, so the profile is selected first and the `restore` profile is selected again afterwards.
+pub async fn read_beverage_priority(
+    ecam: Ecam,
+    profile: u8,
+    restore: u8,
+) -> Result<Vec<MachineEnum<EcamBeverageId>>, EcamError> {
+    let response = with_profile_selected(
+        ecam.clone(),
+        profile,
+        restore,
+        ecam.request(Request::RecipePriorityRead()),
+    )
+    .await?;
+    match response {
+        Response::RecipePriorityRead(priorities) => {
+            Ok(priorities.into_iter().map(MachineEnum::decode).collect())
+        }
+        packet => Err(EcamError::UnexpectedResponse(Box::new(packet))),
+    }
+}
+
+/// Writes the favorite beverages for the given profile, in the order they should be shown on the display.
+pub async fn write_favorite_beverages(
+    ecam: Ecam,
+    profile: u8,
+    beverages: &[EcamBeverageId],
+) -> Result<(), EcamError> {
+    let beverages = beverages.iter().map(|b| (*b).into()).collect();
+    ecam.request(Request::SetFavoriteBeverages(profile, beverages))
+        .await?;
+    Ok(())
+}
+
+/// Lists the order of beverages on the machine's display for the given profile, along with the raw values.
+pub async fn list_beverage_priority(ecam: Ecam, profile: u8, restore: u8) -> Result<(), EcamError> {
+    for (i, beverage) in (1..).zip(read_beverage_priority(ecam, profile, restore).await?) {
+        info!(
+            "{}: {} ({})",
+            i,
+            beverage_to_string(beverage),
+            u8::from(beverage)
+        );
+    }
+    Ok(())
+}
+
+/// Sets the favorite beverages for the given profile.
+pub async fn set_favorite_beverages(
+    ecam: Ecam,
+    profile: u8,
+    beverages: &[EcamBeverageId],
+) -> Result<(), EcamError> {
+    write_favorite_beverages(ecam, profile, beverages).await?;
+    info!(
+        "Favorites for profile {} are now {}",
+        profile,
+        beverages
+            .iter()
+            .map(EcamBeverageId::to_arg_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Ok(())
+}
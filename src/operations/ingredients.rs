@@ -274,6 +274,30 @@ pub struct IngredientCheckError {
     pub range_errors: Vec<(EcamIngredients, String)>,
 }
 
+impl std::fmt::Display for IngredientCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut errors = vec![];
+        if !self.missing.is_empty() {
+            errors.push(format!(
+                "missing {}",
+                self.missing
+                    .iter()
+                    .collect_map_join(", ", |m| m.to_arg_string().unwrap_or(format!("{:?}", m)))
+            ));
+        }
+        if !self.extra.is_empty() {
+            errors.push(format!(
+                "unexpected {}",
+                self.extra
+                    .iter()
+                    .collect_map_join(", ", |e| e.to_arg_string())
+            ));
+        }
+        errors.extend(self.range_errors.iter().map(|(_, s)| s.clone()));
+        f.write_str(&errors.join("; "))
+    }
+}
+
 /// Checks this [`BrewIngredientInfo`] against an [`IngredientRangeInfo`] and returns [`Ok(RecipeInfo)`] if valid.
 pub fn check_ingredients(
     mode: IngredientCheckMode,
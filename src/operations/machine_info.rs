@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use serde_json::{Value, json};
+
+use crate::{
+    ecam::{Ecam, EcamError},
+    operations::{DEFAULT_PROFILE, list_recipies_for},
+    prelude::*,
+    protocol::*,
+};
+
+/// The parameters read raw as part of [`MachineInfo`], from the start of parameter memory. What these values mean is
+/// not known, so they are reported as-is and should not be taken as model information.
+pub const UNKNOWN_PARAMETERS: [u16; 4] = [0x00, 0x04, 0x08, 0x0c];
+
+/// Identifying information for a machine, used to tell apart machines with different firmware.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MachineInfo {
+    /// The raw response to [`Request::Checksum`], or `None` if the machine did not respond.
+    pub checksum: Option<Vec<u8>>,
+    /// The raw values of the [`UNKNOWN_PARAMETERS`] that the machine responded to.
+    pub unknown_parameters: BTreeMap<u16, Vec<u8>>,
+    /// The beverages that the machine has recipes for.
+    pub beverages: Vec<EcamBeverageId>,
+}
+
+impl MachineInfo {
+    /// Formats this information as JSON, with raw bytes as hex strings.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "checksum": self.checksum.as_ref().map(hex::encode),
+            "unknown_parameters": self
+                .unknown_parameters
+                .iter()
+                .map(|(param, data)| (format!("{:04x}", param), Value::from(hex::encode(data))))
+                .collect::<serde_json::Map<_, _>>(),
+            "beverages": self
+                .beverages
+                .iter()
+                .map(EcamBeverageId::to_arg_string)
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl Display for MachineInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.checksum {
+            Some(checksum) => writeln!(f, "Checksum: {}", hex::encode(checksum))?,
+            None => writeln!(f, "Checksum: unknown")?,
+        }
+        for (param, data) in &self.unknown_parameters {
+            writeln!(f, "Unknown parameter {:04x}: {}", param, hex::encode(data))?;
+        }
+        write!(
+            f,
+            "Beverages: {}",
+            self.beverages
+                .iter()
+                .map(EcamBeverageId::to_arg_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+/// Collects the checksum, [`UNKNOWN_PARAMETERS`] and available beverages from the device. Requests that time out are
+/// left out, as not every machine supports them.
+pub async fn read_machine_info(ecam: Ecam) -> Result<MachineInfo, EcamError> {
+    let mut info = MachineInfo::default();
+    match ecam.request(Request::Checksum()).await {
+        Ok(Response::Checksum(checksum)) => info.checksum = Some(checksum),
+        Ok(packet) => return Err(EcamError::UnexpectedResponse(Box::new(packet))),
+        Err(EcamError::Timeout) => warning!("No checksum received"),
+        Err(e) => return Err(e),
+    }
+    for param in UNKNOWN_PARAMETERS {
+        match ecam.request(Request::ParameterReadExt(param, 4)).await {
+            Ok(Response::ParameterReadExt(param, data)) => {
+                info.unknown_parameters.insert(param, data);
+            }
+            Ok(packet) => return Err(EcamError::UnexpectedResponse(Box::new(packet))),
+            Err(EcamError::Timeout) => warning!("No packet received for parameter {:04x}", param),
+            Err(e) => return Err(e),
+        }
+    }
+    info.beverages = list_recipies_for(ecam, DEFAULT_PROFILE, None)
+        .await?
+        .recipes
+        .iter()
+        .map(|r| r.beverage)
+        .collect();
+    Ok(info)
+}
+
+/// Prints the [`MachineInfo`] for the device as text, or as JSON.
+pub async fn machine_info(ecam: Ecam, json: bool) -> Result<(), EcamError> {
+    // Wait for device to settle
+    ecam.wait_for_connection().await?;
+    let info = read_machine_info(ecam).await?;
+    if json {
+        println!("{}", info.to_json());
+    } else {
+        println!("{}", info);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format() {
+        let info = MachineInfo {
+            checksum: Some(vec![0x12, 0x34]),
+            unknown_parameters: BTreeMap::from([(4, vec![0, 1, 2, 3])]),
+            beverages: vec![EcamBeverageId::EspressoCoffee, EcamBeverageId::Cappuccino],
+        };
+        assert_eq!(
+            info.to_string(),
+            "Checksum: 1234\nUnknown parameter 0004: 00010203\nBeverages: espressocoffee, cappuccino"
+        );
+        assert_eq!(
+            info.to_json(),
+            json!({
+                "checksum": "1234",
+                "unknown_parameters": { "0004": "00010203" },
+                "beverages": ["espressocoffee", "cappuccino"],
+            })
+        );
+    }
+}
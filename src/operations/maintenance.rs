@@ -0,0 +1,249 @@
+use std::fmt::Display;
+
+use tokio_stream::StreamExt;
+
+use crate::{
+    display,
+    ecam::{Ecam, EcamError, EcamStatus},
+    prelude::*,
+    protocol::*,
+};
+
+/// A maintenance cycle that the machine can run. Cycles are started from the machine's front panel, and are only
+/// followed from here.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MaintenanceCycle {
+    /// Rinse the coffee circuit.
+    Rinse,
+    /// Clean the milk circuit of the carafe.
+    MilkClean,
+    Descale,
+}
+
+/// A manual step, detected from the machine's alarms and switches, that must be done before the cycle can continue.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MaintenanceHint {
+    InsertWaterTank,
+    FillWaterTank,
+    EmptyGroundsContainer,
+    EmptyDripTray,
+    AttachMilkCarafe,
+    TurnKnobToClean,
+}
+
+impl Display for MaintenanceHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MaintenanceHint::InsertWaterTank => "insert the water tank",
+            MaintenanceHint::FillWaterTank => "fill the water tank",
+            MaintenanceHint::EmptyGroundsContainer => "empty the coffee grounds container",
+            MaintenanceHint::EmptyDripTray => "empty the drip tray",
+            MaintenanceHint::AttachMilkCarafe => "attach the milk carafe",
+            MaintenanceHint::TurnKnobToClean => "turn the carafe knob to CLEAN",
+        })
+    }
+}
+
+impl MaintenanceCycle {
+    /// All of the supported maintenance cycles.
+    pub const ALL: [MaintenanceCycle; 3] = [
+        MaintenanceCycle::Rinse,
+        MaintenanceCycle::MilkClean,
+        MaintenanceCycle::Descale,
+    ];
+
+    /// The name of this cycle, as used on the command-line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MaintenanceCycle::Rinse => "rinse",
+            MaintenanceCycle::MilkClean => "milk-clean",
+            MaintenanceCycle::Descale => "descale",
+        }
+    }
+
+    /// Finds a cycle by its case-insensitive name.
+    pub fn lookup(name: &str) -> Option<MaintenanceCycle> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The default amount of time to wait for this cycle to complete, including any manual steps along the way.
+    pub fn default_timeout(&self) -> Duration {
+        match self {
+            MaintenanceCycle::Rinse => Duration::from_secs(120),
+            MaintenanceCycle::MilkClean => Duration::from_secs(300),
+            MaintenanceCycle::Descale => Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// The machine state while this cycle is running.
+    pub fn machine_state(&self) -> EcamMachineState {
+        match self {
+            MaintenanceCycle::Rinse => EcamMachineState::Rinsing,
+            MaintenanceCycle::MilkClean => EcamMachineState::MilkCleaning,
+            MaintenanceCycle::Descale => EcamMachineState::Descaling,
+        }
+    }
+
+    /// Manual steps that the machine can't detect, which must be done before starting this cycle.
+    pub fn instructions(&self) -> &'static [&'static str] {
+        match self {
+            MaintenanceCycle::Rinse => &["Place a cup under the coffee spouts"],
+            MaintenanceCycle::MilkClean => &["Place a container under the milk spout"],
+            MaintenanceCycle::Descale => &[
+                "Remove the water filter, if one is installed",
+                "Empty the water tank and fill it with descaler and water up to the descale mark",
+                "Place a container of at least 2 litres under the spouts",
+            ],
+        }
+    }
+
+    /// The manual steps the machine is waiting for, based on its alarms, switches and accessory.
+    pub fn hints(&self, status: &MonitorStatus) -> Vec<MaintenanceHint> {
+        let alarm = |a| status.alarms.set().contains(&MachineEnum::Value(a));
+        let switch = |s| status.switches.set().contains(&MachineEnum::Value(s));
+        let mut hints = vec![];
+        if switch(EcamMachineSwitch::WaterTankAbsent) {
+            hints.push(MaintenanceHint::InsertWaterTank);
+        } else if alarm(EcamMachineAlarm::EmptyWaterTank)
+            || switch(EcamMachineSwitch::WaterLevelLow)
+        {
+            hints.push(MaintenanceHint::FillWaterTank);
+        }
+        if alarm(EcamMachineAlarm::CoffeeWasteContainerFull) {
+            hints.push(MaintenanceHint::EmptyGroundsContainer);
+        }
+        if alarm(EcamMachineAlarm::EmptyDripTray) {
+            hints.push(MaintenanceHint::EmptyDripTray);
+        }
+        if *self == MaintenanceCycle::MilkClean {
+            match status.accessory {
+                MachineEnum::Value(EcamAccessory::MilkClean) => {}
+                MachineEnum::Value(EcamAccessory::Milk) => {
+                    hints.push(MaintenanceHint::TurnKnobToClean)
+                }
+                _ => hints.push(MaintenanceHint::AttachMilkCarafe),
+            }
+        }
+        hints
+    }
+}
+
+/// Waits for the status to match `done`, displaying progress and announcing manual steps as they come up.
+async fn follow_maintenance(
+    ecam: Ecam,
+    cycle: MaintenanceCycle,
+    deadline: tokio::time::Instant,
+    mut done: impl FnMut(&MonitorStatus) -> bool,
+) -> Result<(), EcamError> {
+    let mut stream = Box::pin(ecam.status_stream().await?);
+    let mut last_hints = None;
+    loop {
+        let status = match tokio::time::timeout_at(deadline, stream.next()).await {
+            Err(_) => return Err(EcamError::Timeout),
+            Ok(None) => return Err(EcamError::Disconnected),
+            Ok(Some(status)) => status,
+        };
+        display::display_status(EcamStatus::from_status(&status));
+        let hints = cycle.hints(&status);
+        if last_hints.as_ref() != Some(&hints) {
+            for hint in &hints {
+                info!("Next step: {}", hint);
+            }
+            last_hints = Some(hints);
+        }
+        if done(&status) {
+            display::clear_status();
+            return Ok(());
+        }
+    }
+}
+
+/// Watches a maintenance cycle started from the machine's front panel: waits for the manual steps the
+/// machine reports to be done, waits for the machine to enter the cycle's [`MaintenanceCycle::machine_state`], and then
+/// follows it to completion, announcing any manual steps the machine asks for along the way (ie: refilling the tank
+/// while descaling). The [`MaintenanceCycle::instructions`] should be shown to the user beforehand.
+pub async fn watch_maintenance(
+    ecam: Ecam,
+    cycle: MaintenanceCycle,
+    timeout: Duration,
+) -> Result<(), EcamError> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let state = ecam.current_state().await?;
+    if let EcamStatus::StandBy | EcamStatus::TurningOn(_) | EcamStatus::ShuttingDown(_) = state {
+        info!(
+            "Machine is in state {:?}, so the {} cannot start",
+            state,
+            cycle.name()
+        );
+        return Err(EcamError::NotReady(state));
+    }
+
+    let running = |s: &MonitorStatus| s.state == cycle.machine_state();
+    follow_maintenance(ecam.clone(), cycle, deadline, |s| {
+        running(s) || cycle.hints(s).is_empty()
+    })
+    .await?;
+    info!("Start the {} from the machine's front panel", cycle.name());
+    follow_maintenance(ecam.clone(), cycle, deadline, running).await?;
+    info!("The {} has started", cycle.name());
+    follow_maintenance(ecam, cycle, deadline, |s| !running(s)).await?;
+    info!("The {} is complete", cycle.name());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::*;
+
+    fn status(
+        accessory: EcamAccessory,
+        switches: &[EcamMachineSwitch],
+        alarms: &[EcamMachineAlarm],
+    ) -> MonitorStatus {
+        MonitorStatus {
+            state: EcamMachineState::ReadyOrDispensing.into(),
+            accessory: accessory.into(),
+            switches: SwitchSet::of(switches),
+            alarms: SwitchSet::of(alarms),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case(MaintenanceCycle::Rinse, status(EcamAccessory::None, &[], &[]), &[])]
+    #[case(MaintenanceCycle::Rinse, status(EcamAccessory::None, &[EcamMachineSwitch::WaterTankAbsent], &[EcamMachineAlarm::EmptyWaterTank]), &[MaintenanceHint::InsertWaterTank])]
+    #[case(MaintenanceCycle::Descale, status(EcamAccessory::None, &[], &[EcamMachineAlarm::EmptyWaterTank, EcamMachineAlarm::EmptyDripTray]), &[MaintenanceHint::FillWaterTank, MaintenanceHint::EmptyDripTray])]
+    #[case(MaintenanceCycle::MilkClean, status(EcamAccessory::None, &[], &[]), &[MaintenanceHint::AttachMilkCarafe])]
+    #[case(MaintenanceCycle::MilkClean, status(EcamAccessory::Milk, &[], &[EcamMachineAlarm::CoffeeWasteContainerFull]), &[MaintenanceHint::EmptyGroundsContainer, MaintenanceHint::TurnKnobToClean])]
+    #[case(MaintenanceCycle::MilkClean, status(EcamAccessory::MilkClean, &[], &[]), &[])]
+    fn hints(
+        #[case] cycle: MaintenanceCycle,
+        #[case] status: MonitorStatus,
+        #[case] expected: &[MaintenanceHint],
+    ) {
+        assert_eq!(cycle.hints(&status), expected);
+    }
+
+    #[tokio::test]
+    async fn watch_rinse_simulator() {
+        let driver = crate::ecam::get_ecam_simulator(&crate::ecam::EcamId::Simulator(
+            "sim[rinse]".to_owned(),
+        ))
+        .await
+        .expect("Failed to create simulator");
+        let ecam = Ecam::new(Box::new(driver), false).await;
+        watch_maintenance(ecam, MaintenanceCycle::Rinse, Duration::from_secs(30))
+            .await
+            .expect("Failed to watch the rinse");
+    }
+
+    #[test]
+    fn lookup() {
+        for cycle in MaintenanceCycle::ALL {
+            assert_eq!(MaintenanceCycle::lookup(cycle.name()), Some(cycle));
+        }
+    }
+}
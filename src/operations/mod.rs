@@ -1,15 +1,33 @@
 //! Coffee-related operations: brewing, monitoring, etc.
 
+mod bean;
 mod brew;
+mod favorites;
 mod ingredients;
+mod machine_info;
+mod maintenance;
 mod monitor;
 mod parameter;
+mod pin;
 mod power;
+mod profile;
 mod recipe_list;
+mod recipe_name;
+mod statistics;
+mod stats_history;
 
+pub use bean::*;
 pub use brew::*;
+pub use favorites::*;
 pub use ingredients::*;
+pub use machine_info::*;
+pub use maintenance::*;
 pub use monitor::*;
 pub use parameter::*;
+pub use pin::*;
 pub use power::*;
+pub use profile::*;
 pub use recipe_list::*;
+pub use recipe_name::*;
+pub use statistics::*;
+pub use stats_history::*;
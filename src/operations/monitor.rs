@@ -3,6 +3,7 @@ use std::time::Instant;
 
 use crate::display::*;
 use crate::ecam::{Ecam, EcamError};
+use crate::protocol::{MonitorStatus, MonitorVersion};
 
 pub async fn monitor(ecam: Ecam) -> Result<(), EcamError> {
     let mut state = ecam.current_state().await?;
@@ -21,3 +22,71 @@ pub async fn monitor(ecam: Ecam) -> Result<(), EcamError> {
 
     Ok(())
 }
+
+/// The header for the CSV written by [`monitor_diff_unknown`].
+const DIFF_UNKNOWN_HEADER: &str =
+    "timestamp_ms,state,progress,percentage,unknown0,unknown1,unknown2,unknown3,unknown4";
+
+/// Formats a CSV row for [`monitor_diff_unknown`].
+fn diff_unknown_row(timestamp_ms: u128, status: &MonitorStatus) -> String {
+    let mut row = format!(
+        "{},{:?},{},{}",
+        timestamp_ms, status.state, status.progress, status.percentage
+    );
+    for unknown in &status.unknown {
+        row += &format!(",{}", unknown);
+    }
+    row
+}
+
+/// Writes a CSV row to stdout every time the undecoded bytes of the monitor response change, alongside the state,
+/// progress and percentage they changed with. This is used to reverse-engineer the meaning of the `unknown0` to
+/// `unknown4` fields of [`crate::protocol::MonitorV2Response`] by collecting logs across many brews.
+pub async fn monitor_diff_unknown(ecam: Ecam) -> Result<(), EcamError> {
+    let mut statuses = ecam.status_stream().await?;
+    let mut last: Option<Vec<u8>> = None;
+    println!("{}", DIFF_UNKNOWN_HEADER);
+    while let Some(status) = statuses.next().await {
+        if status.version != Some(MonitorVersion::V2) {
+            warning!(
+                "Machine speaks monitor {:?}, which has no unknown fields",
+                status.version
+            );
+            break;
+        }
+        if last.as_ref() != Some(&status.unknown) {
+            let timestamp_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            println!("{}", diff_unknown_row(timestamp_ms, &status));
+            last = Some(status.unknown);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::*;
+
+    #[test]
+    fn test_diff_unknown_row() {
+        let status = MonitorStatus::from(MonitorV2Response {
+            state: EcamMachineState::ReadyOrDispensing.into(),
+            progress: 3,
+            percentage: 45,
+            unknown1: 7,
+            unknown4: 255,
+            ..Default::default()
+        });
+        let row = diff_unknown_row(1000, &status);
+        assert_eq!(row, "1000,ReadyOrDispensing,3,45,0,7,0,0,255");
+        assert_eq!(
+            row.split(',').count(),
+            DIFF_UNKNOWN_HEADER.split(',').count()
+        );
+    }
+}
@@ -1,66 +1,214 @@
 use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     ecam::{Ecam, EcamError, EcamOutput},
     prelude::*,
-    protocol::{Request, Response},
+    protocol::{EcamDriverPacket, PartialEncode, Request, Response, hexdump},
 };
 
-pub async fn read_parameter_memory(ecam: Ecam) -> Result<(), EcamError> {
-    let mut tap = ecam.packet_tap().await?;
+/// Prints the packet that [`write_parameter`] would send, without sending it.
+pub fn write_parameter_dry_run(param: u16, data: Vec<u8>) {
+    let req = Request::ParameterWrite(param, data);
+    info!("Would send {:?}", req);
+    info!(
+        "{}",
+        hexdump(&EcamDriverPacket::from_vec(req.encode()).packetize())
+    );
+}
+
+/// Writes a parameter to the device and waits for the acknowledgement. No parameter is known to be safe to write, so
+/// writes are refused unless `force` is set. The write is sent once, as a lost acknowledgement doesn't mean that the
+/// write was lost too.
+pub async fn write_parameter(
+    ecam: Ecam,
+    param: u16,
+    data: Vec<u8>,
+    force: bool,
+) -> Result<(), EcamError> {
+    if !force {
+        return Err(EcamError::InvalidArgument(format!(
+            "Parameter {} is not known to be safe to write, pass --force to write it anyways",
+            param
+        )));
+    }
+
+    match ecam
+        .request_with_attempts(Request::ParameterWrite(param, data.clone()), 1)
+        .await
+    {
+        Ok(Response::ParameterWrite(_, ack_data)) => {
+            if ack_data != data {
+                warning!(
+                    "Parameter {} acknowledged with different data: {:02x?}",
+                    param,
+                    ack_data
+                );
+            }
+            info!("Wrote parameter {}", param);
+            Ok(())
+        }
+        Ok(packet) => Err(EcamError::UnexpectedResponse(Box::new(packet))),
+        Err(e) => {
+            info!("No acknowledgement received for parameter {}", param);
+            Err(e)
+        }
+    }
+}
+
+/// A block of parameter memory, as stored in a parameter dump file (one JSON object per line).
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ParameterBlock {
+    pub address: u16,
+    #[serde(with = "hex")]
+    pub data: Vec<u8>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Writes parameter blocks to a dump file, one JSON object per line.
+pub fn write_parameter_dump(path: &Path, blocks: &[ParameterBlock]) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    for block in blocks {
+        writeln!(file, "{}", serde_json::to_string(block)?)?;
+    }
+    file.flush()
+}
+
+/// Reads the parameter blocks from a dump file written by [`write_parameter_dump`]. Lines that fail to parse are
+/// skipped.
+pub fn read_parameter_dump(path: &Path) -> std::io::Result<Vec<ParameterBlock>> {
+    let file = std::fs::File::open(path)?;
+    let mut blocks = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(block) => blocks.push(block),
+            Err(e) => warning!("Skipping line {} of {}: {}", i + 1, path.display(), e),
+        }
+    }
+    Ok(blocks)
+}
+
+/// A parameter that differs between two dumps. A side is `None` if that dump has no block for the address.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParameterChange {
+    pub address: u16,
+    pub before: Option<Vec<u8>>,
+    pub after: Option<Vec<u8>>,
+}
+
+/// Compares two parameter dumps, returning the changed blocks in address order.
+pub fn diff_parameter_dumps(
+    before: &[ParameterBlock],
+    after: &[ParameterBlock],
+) -> Vec<ParameterChange> {
+    let mut changes: BTreeMap<u16, ParameterChange> = BTreeMap::new();
+    for block in before {
+        changes
+            .entry(block.address)
+            .or_insert(ParameterChange {
+                address: block.address,
+                before: None,
+                after: None,
+            })
+            .before = Some(block.data.clone());
+    }
+    for block in after {
+        changes
+            .entry(block.address)
+            .or_insert(ParameterChange {
+                address: block.address,
+                before: None,
+                after: None,
+            })
+            .after = Some(block.data.clone());
+    }
+    changes
+        .into_values()
+        .filter(|c| c.before != c.after)
+        .collect()
+}
+
+/// Prints the blocks that differ between two parameter dump files.
+pub fn param_diff(before: &Path, after: &Path) -> Result<(), EcamError> {
+    let changes = diff_parameter_dumps(&read_parameter_dump(before)?, &read_parameter_dump(after)?);
+    if changes.is_empty() {
+        println!("No parameters changed");
+    }
+    let format = |data: &Option<Vec<u8>>| data.as_ref().map_or("missing".to_owned(), hex::encode);
+    for change in changes {
+        println!(
+            "{:04x}: {} -> {}",
+            change.address,
+            format(&change.before),
+            format(&change.after)
+        );
+    }
+    Ok(())
+}
+
+/// Reads the whole of parameter memory, printing a hexdump of the non-zero blocks as it goes. The blocks that were
+/// read, including the zero blocks, are returned so they can be written with [`write_parameter_dump`].
+pub async fn read_parameter_memory(ecam: Ecam) -> Result<Vec<ParameterBlock>, EcamError> {
+    let mut blocks = vec![];
     let mut last_all_zero = false;
     for i in 0..0x1000 {
         let param = i * 4;
-        ecam.write_request(Request::ParameterReadExt(param, 4))
-            .await?;
-        let now = std::time::Instant::now();
-        while now.elapsed() < Duration::from_millis(500) {
-            match tokio::time::timeout(Duration::from_millis(50), tap.next()).await {
-                Err(_) => {}
-                Ok(None) => {
-                    eprintln!("No packet received for {:04x}", param);
-                    return Err(EcamError::Unknown);
+        match ecam.request(Request::ParameterReadExt(param, 4)).await {
+            Err(EcamError::Timeout) => {
+                eprintln!("No packet received for {:04x}", param);
+            }
+            Err(e) => return Err(e),
+            Ok(Response::ParameterReadExt(param, data)) => {
+                blocks.push(ParameterBlock {
+                    address: param,
+                    data: data.clone(),
+                    timestamp: Utc::now(),
+                });
+                let all_zero = data.iter().all(|d| *d == 0);
+                if all_zero {
+                    if !last_all_zero {
+                        println!("...");
+                    }
+                    last_all_zero = all_zero;
+                    continue;
                 }
-                Ok(Some(x)) => {
-                    if let Some(packet) = x.take_packet() {
-                        if let Response::ParameterReadExt(param, data) = packet {
-                            let all_zero = data.iter().all(|d| *d == 0);
-                            if all_zero {
-                                if last_all_zero {
-                                    break;
-                                }
-                                last_all_zero = all_zero;
-                                println!("...");
-                                break;
-                            }
-                            last_all_zero = all_zero;
-                            print!("{:04x}: ", param);
-                            for d in &data {
-                                print!("{:02x}", d);
-                            }
-                            print!("  ");
-                            for d in &data {
-                                if *d >= 32 && *d < 127 {
-                                    print!("{}", *d as char);
-                                } else {
-                                    print!(".");
-                                }
-                            }
-                            println!();
-                            break;
-                        } else {
-                            eprintln!("Unexpected packet: {:?}", packet);
-                            return Err(EcamError::Unknown);
-                        }
+                last_all_zero = all_zero;
+                print!("{:04x}: ", param);
+                for d in &data {
+                    print!("{:02x}", d);
+                }
+                print!("  ");
+                for d in &data {
+                    if *d >= 32 && *d < 127 {
+                        print!("{}", *d as char);
                     } else {
-                        eprintln!("No packet received for {:04x}", param);
-                        return Err(EcamError::Unknown);
+                        print!(".");
                     }
                 }
+                println!();
             }
+            Ok(packet) => return Err(EcamError::UnexpectedResponse(Box::new(packet))),
         }
     }
 
+    Ok(blocks)
+}
+
+/// Reads the whole of parameter memory as [`read_parameter_memory`] does, optionally writing it to a dump file.
+pub async fn dump_parameter_memory(ecam: Ecam, output: Option<&Path>) -> Result<(), EcamError> {
+    let blocks = read_parameter_memory(ecam).await?;
+    if let Some(output) = output {
+        write_parameter_dump(output, &blocks)?;
+        info!("Wrote {} blocks to {}", blocks.len(), output.display());
+    }
     Ok(())
 }
 
@@ -117,60 +265,95 @@ pub async fn read_statistic(ecam: Ecam, param: u16, len: u8) -> Result<(), EcamE
     Ok(())
 }
 
-/// Read all statistics from the device. The machine behaves strangely:
-///
-///  - It will never return invalid statistics, so if you ask for statistic "1"
-///    and it doesn't exist, it'll jump to the next valid statistic.
-///  - It always behaves as if it read N statistics (N appears to be 9?) and
-///    then truncates the list to the length specified. To read all statistics,
-///    you need to use the maximum length because asking for the last statistic
-///    will result in the machine returning MAX - STAT_BATCH_SIZE as the first one
-///    and then truncating to the length specified.
-///
-/// So what we need to do is:
-///
-/// Ask for stat 1, length 16. This returns the first statistic clamped to the internal length (9).
-/// We then ask for the _last_ statistic in that batch, length 16, which gets the next batch. Continue until
-/// we get a response of zero length.
-pub async fn read_statistics(ecam: Ecam) -> Result<(), EcamError> {
-    let mut tap = ecam.packet_tap().await?;
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    // let mut last_stat = 0;
-    let mut current_stat = 1;
-    const BATCH_SIZE: u8 = 16;
+    fn block(address: u16, data: &[u8]) -> ParameterBlock {
+        ParameterBlock {
+            address,
+            data: data.to_vec(),
+            timestamp: "2026-10-18T09:00:00Z".parse().unwrap(),
+        }
+    }
 
-    let mut all_stats = BTreeMap::new();
+    #[test]
+    fn block_round_trip() {
+        let b = block(0x3e, &[0, 0, 0, 30]);
+        let line = serde_json::to_string(&b).unwrap();
+        assert_eq!(
+            line,
+            r#"{"address":62,"data":"0000001e","timestamp":"2026-10-18T09:00:00Z"}"#
+        );
+        assert_eq!(serde_json::from_str::<ParameterBlock>(&line).unwrap(), b);
+    }
 
-    loop {
-        ecam.write_request(Request::StatisticsRead(current_stat, BATCH_SIZE))
-            .await?;
-        let now = std::time::Instant::now();
-        while now.elapsed() < Duration::from_millis(500) {
-            match tokio::time::timeout(Duration::from_millis(50), tap.next()).await {
-                Err(_) => {}
-                Ok(None) => {
-                    eprintln!("No packet received for {:04x}", current_stat);
-                    return Err(EcamError::Unknown);
-                }
-                Ok(Some(x)) => {
-                    if let Some(packet) = x.take_packet() {
-                        if let Response::StatisticsRead(stats) = packet {
-                            if stats.is_empty() {
-                                return Ok(());
-                            }
-                            for stat in &stats {
-                                if all_stats.insert(stat.stat, *stat).is_none() {
-                                    println!(
-                                        "{:>5}: {:08x} ({})",
-                                        stat.stat, stat.value, stat.value
-                                    );
-                                }
-                            }
-                            current_stat = stats.last().unwrap().stat;
-                        }
-                    }
-                }
-            }
-        }
+    #[tokio::test]
+    async fn dump_and_diff_simulator() {
+        let driver =
+            crate::ecam::get_ecam_simulator(&crate::ecam::EcamId::Simulator("sim[on]".to_owned()))
+                .await
+                .expect("Failed to create simulator");
+        let ecam = Ecam::new(Box::new(driver), false).await;
+        let blocks = read_parameter_memory(ecam).await.expect("Failed to dump");
+        assert_eq!(blocks.len(), 0x1000);
+        assert_eq!(blocks[1].address, 4);
+        assert_eq!(blocks[1].data, vec![0, 0, 0, 4]);
+
+        let dir = std::env::temp_dir().join(format!("longshot-param-dump-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (before, after) = (dir.join("before.jsonl"), dir.join("after.jsonl"));
+        write_parameter_dump(&before, &blocks).unwrap();
+        let mut changed = blocks.clone();
+        changed[1].data = vec![0, 0, 0, 5];
+        write_parameter_dump(&after, &changed).unwrap();
+        // Lines that fail to parse are skipped rather than failing the whole dump
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&after)
+            .unwrap();
+        writeln!(file, "not json").unwrap();
+        drop(file);
+
+        assert_eq!(read_parameter_dump(&before).unwrap(), blocks);
+        assert_eq!(read_parameter_dump(&after).unwrap(), changed);
+        assert_eq!(
+            diff_parameter_dumps(
+                &read_parameter_dump(&before).unwrap(),
+                &read_parameter_dump(&after).unwrap()
+            ),
+            vec![ParameterChange {
+                address: 4,
+                before: Some(vec![0, 0, 0, 4]),
+                after: Some(vec![0, 0, 0, 5]),
+            }]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn diff() {
+        let before = [block(0, &[1]), block(4, &[2]), block(8, &[3])];
+        let after = [block(0, &[1]), block(4, &[5]), block(12, &[4])];
+        assert_eq!(
+            diff_parameter_dumps(&before, &after),
+            vec![
+                ParameterChange {
+                    address: 4,
+                    before: Some(vec![2]),
+                    after: Some(vec![5]),
+                },
+                ParameterChange {
+                    address: 8,
+                    before: Some(vec![3]),
+                    after: None,
+                },
+                ParameterChange {
+                    address: 12,
+                    before: None,
+                    after: Some(vec![4]),
+                },
+            ]
+        );
     }
 }
@@ -0,0 +1,31 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::*,
+};
+
+/// The PIN lock (child lock) state of the machine.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PinState {
+    /// Is the PIN lock enabled? Beverages may not be dispensed remotely while the machine is locked.
+    pub enabled: bool,
+}
+
+/// Reads the PIN lock state from the device.
+pub async fn read_pin_state(ecam: Ecam) -> Result<PinState, EcamError> {
+    match ecam.request(Request::PinRead()).await? {
+        Response::PinRead(enabled) => Ok(PinState {
+            enabled: enabled != 0,
+        }),
+        packet => Err(EcamError::UnexpectedResponse(Box::new(packet))),
+    }
+}
+
+pub async fn show_pin_status(ecam: Ecam) -> Result<(), EcamError> {
+    if read_pin_state(ecam).await?.enabled {
+        info!("PIN lock is enabled");
+    } else {
+        info!("PIN lock is disabled");
+    }
+    Ok(())
+}
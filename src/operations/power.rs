@@ -3,42 +3,50 @@ use crate::ecam::{Ecam, EcamError, EcamStatus};
 use crate::prelude::*;
 use crate::protocol::*;
 
+/// The default amount of time to wait for the machine to turn on, including the initial rinse.
+pub const DEFAULT_POWER_ON_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Ensures the machine is ready for an operation, optionally turning it on and waiting up to `timeout` for it to become
+/// ready. Fails with [`EcamError::NotReady`] if the machine is in standby and may not be turned on.
 pub async fn power_on(
     ecam: Ecam,
     allow_off: bool,
     allow_alarms: bool,
     turn_on: bool,
-) -> Result<bool, EcamError> {
+    timeout: Duration,
+) -> Result<(), EcamError> {
     match ecam.current_state().await? {
-        EcamStatus::Ready => {
-            return Ok(true);
-        }
+        EcamStatus::Ready => {}
         EcamStatus::StandBy => {
             if allow_off {
                 info!("Machine is off, but --allow-off will allow us to proceed");
-                return Ok(true);
             } else if !turn_on {
                 info!("Machine is not on, pass --turn-on to turn it on before operation");
+                return Err(EcamError::NotReady(EcamStatus::StandBy));
             } else {
                 info!("Waiting for the machine to turn on...");
                 ecam.write_request(Request::AppControl(AppControl::TurnOn))
                     .await?;
-                ecam.wait_for_state(EcamStatus::Ready, display::display_status)
-                    .await?;
-                return Ok(true);
+                ecam.wait_for_state_with_timeout(
+                    EcamStatus::Ready,
+                    display::display_status,
+                    timeout,
+                )
+                .await?;
             }
         }
         s => {
             if allow_alarms {
-                return Ok(true);
+                return Ok(());
             }
             info!(
                 "Machine is in state {:?}, so we will cowardly refuse to brew coffee",
                 s
             );
+            return Err(EcamError::NotReady(s));
         }
     }
-    Ok(false)
+    Ok(())
 }
 
 pub async fn app_control(ecam: Ecam, a: u8, b: u8) -> Result<(), EcamError> {
@@ -0,0 +1,139 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::*,
+};
+
+/// The number of user profiles stored on the device.
+pub const PROFILE_COUNT: u8 = 3;
+
+/// The profile used when no profile is specified.
+pub const DEFAULT_PROFILE: u8 = 1;
+
+/// A user profile stored on the device.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Profile {
+    /// The 1-based profile number.
+    pub id: u8,
+    pub name: String,
+    pub icon: u8,
+}
+
+/// Reads all of the user profiles from the device.
+pub async fn read_profiles(ecam: Ecam) -> Result<Vec<Profile>, EcamError> {
+    match ecam
+        .request(Request::ProfileNameRead(1, PROFILE_COUNT))
+        .await?
+    {
+        Response::ProfileNameRead(names) => Ok(names
+            .into_iter()
+            .zip(1..)
+            .map(|(name, id)| Profile {
+                id,
+                name: name.name,
+                icon: name.icon,
+            })
+            .collect()),
+        packet => Err(EcamError::UnexpectedResponse(Box::new(packet))),
+    }
+}
+
+/// Finds a profile by number, or by case-insensitive name.
+pub async fn find_profile(ecam: Ecam, profile: &str) -> Result<Profile, EcamError> {
+    let profiles = read_profiles(ecam).await?;
+    let found = if let Ok(id) = profile.parse::<u8>() {
+        profiles.into_iter().find(|p| p.id == id)
+    } else {
+        profiles
+            .into_iter()
+            .find(|p| p.name.eq_ignore_ascii_case(profile))
+    };
+    found.ok_or_else(|| {
+        info!("No profile found matching '{}'", profile);
+        EcamError::NotFound
+    })
+}
+
+/// Resolves a profile number or name to a profile number. Only names require a round-trip to the device.
+pub async fn resolve_profile(ecam: Ecam, profile: &str) -> Result<u8, EcamError> {
+    match profile.parse::<u8>() {
+        Ok(id) if (1..=PROFILE_COUNT).contains(&id) => Ok(id),
+        Ok(id) => {
+            info!("Profile {} is out of range (1-{})", id, PROFILE_COUNT);
+            Err(EcamError::NotFound)
+        }
+        Err(_) => Ok(find_profile(ecam, profile).await?.id),
+    }
+}
+
+/// Writes the name and icon for the given profile number.
+pub async fn write_profile_name(
+    ecam: Ecam,
+    profile: u8,
+    name: &str,
+    icon: u8,
+) -> Result<(), EcamError> {
+    if name.chars().count() > WIDE_STRING_LENGTH {
+        warning!(
+            "Profile name '{}' will be truncated to {} characters",
+            name,
+            WIDE_STRING_LENGTH
+        );
+    }
+    ecam.request(Request::ProfileNameWrite(
+        profile,
+        WideStringWithIcon::new(name, icon),
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Selects the given profile number as the active profile.
+pub async fn select_profile(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
+    ecam.request(Request::ProfileSelection(profile)).await?;
+    Ok(())
+}
+
+/// Runs an operation with the given profile selected, then selects the `restore` profile again whether or not the
+/// operation succeeded. There is no known way to read the active profile from the machine, so the caller must say
+/// which profile is active and should be restored. If that is the given profile, no profile is selected at all.
+pub async fn with_profile_selected<T>(
+    ecam: Ecam,
+    profile: u8,
+    restore: u8,
+    f: impl Future<Output = Result<T, EcamError>>,
+) -> Result<T, EcamError> {
+    if profile == restore {
+        return f.await;
+    }
+    select_profile(ecam.clone(), profile).await?;
+    let res = f.await;
+    match (select_profile(ecam, restore).await, &res) {
+        (Err(e), Ok(_)) => return Err(e),
+        (Err(e), Err(_)) => warning!("Failed to restore profile {}: {}", restore, e),
+        (Ok(()), _) => {}
+    }
+    res
+}
+
+pub async fn list_profiles(ecam: Ecam) -> Result<(), EcamError> {
+    for profile in read_profiles(ecam).await? {
+        info!("{}: {} (icon {})", profile.id, profile.name, profile.icon);
+    }
+    Ok(())
+}
+
+/// Renames and/or re-icons the given profile, keeping whichever of the name or icon is not specified.
+pub async fn rename_profile(
+    ecam: Ecam,
+    profile: &str,
+    name: Option<&str>,
+    icon: Option<u8>,
+) -> Result<(), EcamError> {
+    let current = find_profile(ecam.clone(), profile).await?;
+    let name = name.unwrap_or(&current.name);
+    let icon = icon.unwrap_or(current.icon);
+    write_profile_name(ecam, current.id, name, icon).await?;
+    info!("Profile {} is now {} (icon {})", current.id, name, icon);
+    Ok(())
+}
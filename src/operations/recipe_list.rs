@@ -1,17 +1,20 @@
 use crate::{display, prelude::*};
 use crate::{
     ecam::{Ecam, EcamError},
-    operations::IngredientRangeInfo,
+    operations::{
+        DEFAULT_PROFILE, IngredientRangeInfo, custom_recipe_slot, read_custom_recipe_names,
+    },
     protocol::*,
 };
 use std::collections::HashMap;
 
 /// Accumulates recipe responses, allowing us to fetch them one-at-a-time and account for which ones went missing in transit.
-/// Note that this doesn't support profiles yet and currently requires the use of profile 1.
+/// Recipe quantities are read for a single profile, which defaults to [`DEFAULT_PROFILE`].
 pub struct RecipeAccumulator {
     recipe: HashMap<EcamBeverageId, Vec<RecipeInfo<u16>>>,
     recipe_min_max: HashMap<EcamBeverageId, Vec<RecipeMinMaxInfo>>,
     list: Vec<EcamBeverageId>,
+    profile: u8,
 }
 
 impl Default for RecipeAccumulator {
@@ -32,9 +35,16 @@ impl RecipeAccumulator {
             list: recipes,
             recipe: HashMap::new(),
             recipe_min_max: HashMap::new(),
+            profile: DEFAULT_PROFILE,
         }
     }
 
+    /// Fetches recipe quantities for the given profile number rather than [`DEFAULT_PROFILE`].
+    pub fn with_profile(mut self, profile: u8) -> Self {
+        self.profile = profile;
+        self
+    }
+
     /// Lists the [`EcamBeverageId`]s which we still need to fetch information for.
     pub fn get_remaining_beverages(&self) -> Vec<EcamBeverageId> {
         let mut remaining = vec![];
@@ -54,7 +64,7 @@ impl RecipeAccumulator {
     pub fn get_request_packets(&self, beverage: EcamBeverageId) -> Vec<Request> {
         vec![
             Request::RecipeMinMaxSync(beverage.into()),
-            Request::RecipeQuantityRead(1, beverage.into()),
+            Request::RecipeQuantityRead(self.profile, beverage.into()),
         ]
     }
 
@@ -64,15 +74,15 @@ impl RecipeAccumulator {
         let recipe_min_max = self.recipe_min_max.get(&beverage);
 
         // If the recipe has empty ingredients, we're going to ignore it and say it's complete
-        if let Some(recipe) = recipe {
-            if recipe.is_empty() {
-                return true;
-            }
+        if let Some(recipe) = recipe
+            && recipe.is_empty()
+        {
+            return true;
         }
-        if let Some(recipe_min_max) = recipe_min_max {
-            if recipe_min_max.is_empty() {
-                return true;
-            }
+        if let Some(recipe_min_max) = recipe_min_max
+            && recipe_min_max.is_empty()
+        {
+            return true;
         }
 
         // Otherwise recipes are only complete if we have both recipe and min/max
@@ -85,15 +95,15 @@ impl RecipeAccumulator {
         let recipe_min_max = self.recipe_min_max.get(&beverage);
 
         // If the recipe has empty ingredients, we're going to ignore it and say it's complete
-        if let Some(recipe) = recipe {
-            if recipe.len() == 0 {
-                return true;
-            }
+        if let Some(recipe) = recipe
+            && recipe.is_empty()
+        {
+            return true;
         }
-        if let Some(recipe_min_max) = recipe_min_max {
-            if recipe_min_max.len() == 0 {
-                return true;
-            }
+        if let Some(recipe_min_max) = recipe_min_max
+            && recipe_min_max.is_empty()
+        {
+            return true;
         }
 
         false
@@ -102,8 +112,8 @@ impl RecipeAccumulator {
     /// Accumulate a [`Response`] for the given [`EcamBeverageId`].
     pub fn accumulate_packet(&mut self, expected_beverage: EcamBeverageId, packet: Response) {
         match packet {
-            Response::RecipeQuantityRead(_, beverage, ingredients) => {
-                if beverage == expected_beverage {
+            Response::RecipeQuantityRead(profile, beverage, ingredients) => {
+                if beverage == expected_beverage && profile == self.profile {
                     self.recipe.insert(expected_beverage, ingredients);
                 }
             }
@@ -130,6 +140,7 @@ impl RecipeAccumulator {
             if let (Some(recipe), Some(recipe_min_max)) = (recipe, recipe_min_max) {
                 list.recipes.push(RecipeDetails {
                     beverage: *beverage,
+                    name: None,
                     recipe,
                     recipe_min_max,
                 });
@@ -171,6 +182,8 @@ impl RecipeList {
 #[derive(Clone, Debug)]
 pub struct RecipeDetails {
     pub beverage: EcamBeverageId,
+    /// The user-assigned name, for custom recipes.
+    pub name: Option<String>,
     recipe: Vec<RecipeInfo<u16>>,
     recipe_min_max: Vec<RecipeMinMaxInfo>,
 }
@@ -213,26 +226,53 @@ impl RecipeDetails {
     }
 }
 
-/// Lists recipes for either all recipes, or just the given ones.
+/// Lists recipes for the given profile, for either all recipes, or just the given ones. Custom recipes are named
+/// with the names stored on the device.
 pub async fn list_recipies_for(
     ecam: Ecam,
+    profile: u8,
     recipes: Option<Vec<EcamBeverageId>>,
 ) -> Result<RecipeList, EcamError> {
-    Ok(accumulate_recipies_for(ecam, recipes).await?.take())
+    let mut list = accumulate_recipies_for(ecam.clone(), profile, recipes)
+        .await?
+        .take();
+    if list
+        .recipes
+        .iter()
+        .any(|r| custom_recipe_slot(r.beverage).is_some())
+    {
+        match read_custom_recipe_names(ecam).await {
+            Ok(names) => {
+                for name in names {
+                    if let Some(recipe) = list
+                        .recipes
+                        .iter_mut()
+                        .find(|r| r.beverage == name.beverage)
+                    {
+                        recipe.name = Some(name.name);
+                    }
+                }
+            }
+            // Not all machines support custom recipe names, so we still list the recipes without them
+            Err(EcamError::Timeout) => warning!("Unable to fetch custom recipe names"),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(list)
 }
 
-/// Accumulates recipe min/max and ingredient info for either all recipes, or just the given ones.
+/// Accumulates recipe min/max and ingredient info for the given profile, for either all recipes, or just the given ones.
 pub async fn accumulate_recipies_for(
     ecam: Ecam,
+    profile: u8,
     recipes: Option<Vec<EcamBeverageId>>,
 ) -> Result<RecipeAccumulator, EcamError> {
-    // Get the tap we'll use for reading responses
-    let mut tap = ecam.packet_tap().await?;
     let mut recipes = if let Some(recipes) = recipes {
         RecipeAccumulator::limited_to(recipes)
     } else {
         RecipeAccumulator::new()
-    };
+    }
+    .with_profile(profile);
     let total = recipes.get_remaining_beverages().len();
     for i in 0..3 {
         if i == 0 {
@@ -243,33 +283,20 @@ pub async fn accumulate_recipies_for(
                 recipes.get_remaining_beverages()
             );
         }
-        'outer: for beverage in recipes.get_remaining_beverages() {
-            'inner: for packet in recipes.get_request_packets(beverage) {
+        for beverage in recipes.get_remaining_beverages() {
+            for packet in recipes.get_request_packets(beverage) {
                 crate::display::display_status(crate::ecam::EcamStatus::Fetching(
                     (total - recipes.get_remaining_beverages().len()) * 100 / total,
                 ));
-                let request_id = packet.ecam_request_id();
-                ecam.write_request(packet).await?;
-                let now = std::time::Instant::now();
-                while now.elapsed() < Duration::from_millis(500) {
-                    match tokio::time::timeout(Duration::from_millis(50), tap.next()).await {
-                        Err(_) => {}
-                        Ok(None) => {}
-                        Ok(Some(x)) => {
-                            if let Some(packet) = x.take_packet() {
-                                let response_id = packet.ecam_request_id();
-                                recipes.accumulate_packet(beverage, packet);
-                                // If this recipe is totally complete, move to the next one
-                                if recipes.is_complete(beverage) {
-                                    continue 'outer;
-                                }
-                                // If we got a response for the given request, move to the next packet/beverage
-                                if response_id == request_id {
-                                    continue 'inner;
-                                }
-                            }
-                        }
-                    }
+                match ecam.request(packet).await {
+                    // Missing recipes are re-requested on the next pass
+                    Err(EcamError::Timeout) => {}
+                    Err(e) => return Err(e),
+                    Ok(packet) => recipes.accumulate_packet(beverage, packet),
+                }
+                // If this recipe is totally complete, move to the next one
+                if recipes.is_complete(beverage) {
+                    break;
                 }
             }
         }
@@ -279,13 +306,17 @@ pub async fn accumulate_recipies_for(
     Ok(recipes)
 }
 
-pub async fn list_recipes(ecam: Ecam) -> Result<(), EcamError> {
+pub async fn list_recipes(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
     // Wait for device to settle
     ecam.wait_for_connection().await?;
-    let list = list_recipies_for(ecam, None).await?;
+    let list = list_recipies_for(ecam, profile, None).await?;
     info!("Beverages supported:");
     for recipe in list.recipes {
-        info!("  {}", recipe.to_arg_string());
+        if let Some(name) = &recipe.name {
+            info!("  {}  # {}", recipe.to_arg_string(), name);
+        } else {
+            info!("  {}", recipe.to_arg_string());
+        }
     }
 
     Ok(())
@@ -313,13 +344,13 @@ fn enspacen(b: &[u8]) -> String {
     s
 }
 
-pub async fn list_recipes_detailed(ecam: Ecam) -> Result<(), EcamError> {
+pub async fn list_recipes_detailed(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
     use ariadne::{Color, Config, Label, Report, ReportBuilder, ReportKind, Source};
     const LINE_LIMIT: usize = 100;
 
     // Wait for device to settle
     ecam.wait_for_connection().await?;
-    let list = accumulate_recipies_for(ecam, None).await?;
+    let list = accumulate_recipies_for(ecam, profile, None).await?;
     for beverage in EcamBeverageId::all() {
         let name = &format!("{:?}", beverage);
         let (recipe, minmax) = list.get(beverage);
@@ -404,10 +435,10 @@ pub async fn list_recipes_detailed(ecam: Ecam) -> Result<(), EcamError> {
     Ok(())
 }
 
-pub async fn list_recipes_raw(ecam: Ecam) -> Result<(), EcamError> {
+pub async fn list_recipes_raw(ecam: Ecam, profile: u8) -> Result<(), EcamError> {
     // Wait for device to settle
     ecam.wait_for_connection().await?;
-    let list = accumulate_recipies_for(ecam, None).await?;
+    let list = accumulate_recipies_for(ecam, profile, None).await?;
     let mut s = "".to_owned();
 
     for beverage in EcamBeverageId::all() {
@@ -0,0 +1,152 @@
+use crate::{
+    ecam::{Ecam, EcamError},
+    prelude::*,
+    protocol::*,
+};
+
+/// The number of custom recipe slots stored on the device, corresponding to [`EcamBeverageId::Custom01`] through
+/// [`EcamBeverageId::Custom10`].
+pub const CUSTOM_RECIPE_COUNT: u8 = 10;
+
+/// The maximum number of characters in a custom recipe name.
+pub const CUSTOM_RECIPE_NAME_LENGTH: usize = WIDE_STRING_LENGTH;
+
+/// The name and icon of a custom recipe slot.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CustomRecipeName {
+    pub beverage: EcamBeverageId,
+    pub name: String,
+    pub icon: u8,
+}
+
+/// Maps a 1-based custom recipe slot to its [`EcamBeverageId`].
+fn custom_recipe_beverage(slot: u8) -> Option<EcamBeverageId> {
+    (EcamBeverageId::Custom01 as u8)
+        .checked_add(slot.checked_sub(1)?)
+        .and_then(|id| id.try_into().ok())
+}
+
+/// Maps a custom recipe [`EcamBeverageId`] to its 1-based slot, or `None` if this is not a custom recipe.
+pub fn custom_recipe_slot(beverage: EcamBeverageId) -> Option<u8> {
+    let slot = (beverage as u8).checked_sub(EcamBeverageId::Custom01 as u8)? + 1;
+    (slot <= CUSTOM_RECIPE_COUNT).then_some(slot)
+}
+
+/// Reads the names and icons of all custom recipe slots from the device.
+pub async fn read_custom_recipe_names(ecam: Ecam) -> Result<Vec<CustomRecipeName>, EcamError> {
+    match ecam
+        .request(Request::RecipeNameRead(1, CUSTOM_RECIPE_COUNT))
+        .await?
+    {
+        Response::RecipeNameRead(names) => Ok(names
+            .into_iter()
+            .zip(1..)
+            .filter_map(|(name, slot)| {
+                Some(CustomRecipeName {
+                    beverage: custom_recipe_beverage(slot)?,
+                    name: name.name,
+                    icon: name.icon,
+                })
+            })
+            .collect()),
+        packet => Err(EcamError::UnexpectedResponse(Box::new(packet))),
+    }
+}
+
+/// Resolves a beverage by name, falling back to the case-insensitive name of a custom recipe. Only custom recipe names
+/// require a round-trip to the device.
+pub async fn resolve_beverage(ecam: Ecam, beverage: &str) -> Result<EcamBeverageId, EcamError> {
+    if let Some(beverage) = EcamBeverageId::lookup_by_name_case_insensitive(beverage) {
+        return Ok(beverage);
+    }
+    read_custom_recipe_names(ecam)
+        .await?
+        .into_iter()
+        .find(|r| r.name.eq_ignore_ascii_case(beverage))
+        .map(|r| r.beverage)
+        .ok_or_else(|| {
+            info!("No beverage or custom recipe found matching '{}'", beverage);
+            EcamError::NotFound
+        })
+}
+
+/// Writes the name and icon for the given custom recipe.
+pub async fn write_custom_recipe_name(
+    ecam: Ecam,
+    beverage: EcamBeverageId,
+    name: &str,
+    icon: u8,
+) -> Result<(), EcamError> {
+    let Some(slot) = custom_recipe_slot(beverage) else {
+        return Err(EcamError::InvalidArgument(format!(
+            "{} is not a custom recipe",
+            beverage.to_arg_string()
+        )));
+    };
+    if name.chars().count() > CUSTOM_RECIPE_NAME_LENGTH {
+        warning!(
+            "Recipe name '{}' will be truncated to {} characters",
+            name,
+            CUSTOM_RECIPE_NAME_LENGTH
+        );
+    }
+    ecam.request(Request::RecipeNameWrite(
+        slot,
+        WideStringWithIcon::new(name, icon),
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Renames and/or re-icons the given custom recipe, keeping whichever of the name or icon is not specified.
+pub async fn rename_custom_recipe(
+    ecam: Ecam,
+    beverage: &str,
+    name: Option<&str>,
+    icon: Option<u8>,
+) -> Result<(), EcamError> {
+    let beverage = resolve_beverage(ecam.clone(), beverage).await?;
+    let current = read_custom_recipe_names(ecam.clone())
+        .await?
+        .into_iter()
+        .find(|r| r.beverage == beverage)
+        .ok_or_else(|| {
+            EcamError::InvalidArgument(format!(
+                "{} is not a custom recipe",
+                beverage.to_arg_string()
+            ))
+        })?;
+    let name = name.unwrap_or(&current.name);
+    let icon = icon.unwrap_or(current.icon);
+    write_custom_recipe_name(ecam, beverage, name, icon).await?;
+    info!(
+        "Recipe {} is now {} (icon {})",
+        beverage.to_arg_string(),
+        name,
+        icon
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(1, Some(EcamBeverageId::Custom01))]
+    #[case(10, Some(EcamBeverageId::Custom10))]
+    #[case(0, None)]
+    #[case(11, None)]
+    fn custom_recipe_slots(#[case] slot: u8, #[case] beverage: Option<EcamBeverageId>) {
+        assert_eq!(custom_recipe_beverage(slot), beverage);
+        if let Some(beverage) = beverage {
+            assert_eq!(custom_recipe_slot(beverage), Some(slot));
+        }
+    }
+
+    #[test]
+    fn not_custom_recipe() {
+        assert_eq!(custom_recipe_slot(EcamBeverageId::EspressoCoffee), None);
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ecam::{Ecam, EcamError},
+    protocol::{Request, Response, Statistic},
+};
+
+/// Read all statistics from the device, ordered by ID. The machine behaves strangely:
+///
+///  - It will never return invalid statistics, so if you ask for statistic "1"
+///    and it doesn't exist, it'll jump to the next valid statistic.
+///  - It always behaves as if it read N statistics (N appears to be 9?) and
+///    then truncates the list to the length specified. To read all statistics,
+///    you need to use the maximum length because asking for the last statistic
+///    will result in the machine returning MAX - STAT_BATCH_SIZE as the first one
+///    and then truncating to the length specified.
+///
+/// So what we need to do is:
+///
+/// Ask for stat 1, length 16. This returns the first statistic clamped to the internal length (9).
+/// We then ask for the _last_ statistic in that batch, length 16, which gets the next batch. Continue until
+/// we get a response of zero length.
+pub async fn read_statistics(ecam: Ecam) -> Result<Vec<Statistic>, EcamError> {
+    let mut current_stat = 1;
+    const BATCH_SIZE: u8 = 16;
+
+    let mut all_stats = BTreeMap::new();
+
+    loop {
+        match ecam
+            .request(Request::StatisticsRead(current_stat, BATCH_SIZE))
+            .await?
+        {
+            Response::StatisticsRead(stats) => {
+                let Some(last) = stats.last() else {
+                    return Ok(all_stats.into_values().collect());
+                };
+                current_stat = last.stat;
+                for stat in stats {
+                    all_stats.entry(stat.stat).or_insert(stat);
+                }
+            }
+            packet => return Err(EcamError::UnexpectedResponse(Box::new(packet))),
+        }
+    }
+}
+
+/// Prints all statistics from the device as ID, hex value and value.
+pub async fn list_statistics(ecam: Ecam) -> Result<(), EcamError> {
+    for stat in read_statistics(ecam).await? {
+        println!("{:>5}: {:08x} ({})", stat.stat, stat.value, stat.value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_simulator() {
+        let driver =
+            crate::ecam::get_ecam_simulator(&crate::ecam::EcamId::Simulator("sim[on]".to_owned()))
+                .await
+                .expect("Failed to create simulator");
+        let ecam = Ecam::new(Box::new(driver), false).await;
+        let stats = read_statistics(ecam).await.expect("Failed to read");
+        assert_eq!(
+            stats.iter().map(|s| s.stat).collect::<Vec<_>>(),
+            (1..=12).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            stats[1],
+            Statistic {
+                stat: 2,
+                value: 200
+            }
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ecam::{Ecam, EcamError},
+    operations::read_statistics,
+    prelude::*,
+};
+
+/// A timestamped copy of all of the statistics on the device, stored as one line of JSON in the history file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StatisticsSnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub stats: BTreeMap<u16, u32>,
+}
+
+/// The default location of the statistics history: `longshot/statistics.jsonl` in the user's data directory.
+pub fn default_stats_history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("longshot").join("statistics.jsonl"))
+}
+
+/// Reads all statistics from the device as a [`StatisticsSnapshot`] taken now.
+pub async fn take_stats_snapshot(ecam: Ecam) -> Result<StatisticsSnapshot, EcamError> {
+    let stats = read_statistics(ecam).await?;
+    Ok(StatisticsSnapshot {
+        timestamp: Utc::now(),
+        stats: stats.into_iter().map(|s| (s.stat, s.value)).collect(),
+    })
+}
+
+/// Appends a snapshot to the history file, creating the file and its directory if needed.
+pub fn append_stats_snapshot(path: &Path, snapshot: &StatisticsSnapshot) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(snapshot)?)?;
+    Ok(())
+}
+
+/// Reads all snapshots from the history file, in the order they were taken. Lines that fail to parse are skipped.
+pub fn read_stats_history(path: &Path) -> std::io::Result<Vec<StatisticsSnapshot>> {
+    let file = std::fs::File::open(path)?;
+    let mut snapshots = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => warning!("Skipping line {} of {}: {}", i + 1, path.display(), e),
+        }
+    }
+    snapshots.sort_by_key(|s: &StatisticsSnapshot| s.timestamp);
+    Ok(snapshots)
+}
+
+/// The change in statistics between two snapshots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatisticsDelta {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// The change in each statistic that changed, by ID. Counters that were reset show up as negative.
+    pub changes: BTreeMap<u16, i64>,
+}
+
+impl StatisticsDelta {
+    /// Computes the change between two snapshots. Statistics missing from either snapshot are ignored.
+    pub fn between(from: &StatisticsSnapshot, to: &StatisticsSnapshot) -> Self {
+        let changes = to
+            .stats
+            .iter()
+            .filter_map(|(stat, value)| {
+                let before = from.stats.get(stat)?;
+                let change = *value as i64 - *before as i64;
+                (change != 0).then_some((*stat, change))
+            })
+            .collect();
+        StatisticsDelta {
+            from: from.timestamp,
+            to: to.timestamp,
+            changes,
+        }
+    }
+
+    /// The number of days between the two snapshots.
+    pub fn days(&self) -> f64 {
+        (self.to - self.from).num_seconds() as f64 / 86400.0
+    }
+}
+
+/// Finds the timestamp of the first snapshot after the most recent descale in the history, if any, given the ID of the
+/// statistic that counts descales. No ID is known to count descales on every model, so the caller must provide it.
+pub fn last_descale(history: &[StatisticsSnapshot], descale_stat: u16) -> Option<DateTime<Utc>> {
+    history
+        .windows(2)
+        .rev()
+        .find(|w| {
+            w[1].stats.get(&descale_stat).unwrap_or(&0)
+                > w[0].stats.get(&descale_stat).unwrap_or(&0)
+        })
+        .map(|w| w[1].timestamp)
+}
+
+/// Reads a snapshot from the device and appends it to the history file.
+pub async fn record_stats_snapshot(ecam: Ecam, path: &Path) -> Result<(), EcamError> {
+    let snapshot = take_stats_snapshot(ecam).await?;
+    append_stats_snapshot(path, &snapshot)?;
+    info!(
+        "Recorded {} statistics to {}",
+        snapshot.stats.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Prints the changes between the first snapshot taken at or after `since` and the last snapshot taken at or before
+/// `until` by statistic ID. The time since the last descale is only shown if the ID of the statistic that counts
+/// descales is given.
+pub fn show_stats_diff(
+    path: &Path,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    descale_stat: Option<u16>,
+) -> Result<(), EcamError> {
+    let history = read_stats_history(path)?;
+    let from = history
+        .iter()
+        .find(|s| since.is_none_or(|since| s.timestamp >= since));
+    let to = history
+        .iter()
+        .rev()
+        .find(|s| until.is_none_or(|until| s.timestamp <= until));
+    let (Some(from), Some(to)) = (from, to) else {
+        return Err(EcamError::InvalidArgument(format!(
+            "Not enough snapshots in {}, run 'stats snapshot' to record one",
+            path.display()
+        )));
+    };
+    if from.timestamp >= to.timestamp {
+        return Err(EcamError::InvalidArgument(
+            "At least two snapshots are required in the requested period".to_owned(),
+        ));
+    }
+
+    let delta = StatisticsDelta::between(from, to);
+    let days = delta.days();
+    println!(
+        "From {} to {} ({:.1} days)",
+        delta.from.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        delta.to.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        days
+    );
+    if delta.changes.is_empty() {
+        println!("No statistics changed");
+    }
+    for (stat, change) in &delta.changes {
+        let per_day = *change as f64 / days;
+        println!("{:>5}: {:+} ({:.1}/day)", stat, change, per_day);
+    }
+    if let Some(descale_stat) = descale_stat {
+        match last_descale(&history, descale_stat) {
+            Some(timestamp) => println!(
+                "Last descale: {} days ago",
+                (Utc::now() - timestamp).num_days()
+            ),
+            None => println!("Last descale: not recorded in the history"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn snapshot(timestamp: &str, stats: &[(u16, u32)]) -> StatisticsSnapshot {
+        StatisticsSnapshot {
+            timestamp: timestamp.parse().unwrap(),
+            stats: stats.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let s = snapshot("2026-10-11T09:00:00Z", &[(2, 100), (7, 3)]);
+        let line = serde_json::to_string(&s).unwrap();
+        assert_eq!(
+            line,
+            r#"{"timestamp":"2026-10-11T09:00:00Z","stats":{"2":100,"7":3}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<StatisticsSnapshot>(&line).unwrap(),
+            s
+        );
+    }
+
+    #[test]
+    fn delta() {
+        let from = snapshot("2026-10-11T09:00:00Z", &[(2, 100), (3, 50), (8, 10)]);
+        let to = snapshot(
+            "2026-10-18T09:00:00Z",
+            &[(2, 114), (3, 50), (8, 13), (9, 1)],
+        );
+        let delta = StatisticsDelta::between(&from, &to);
+        assert_eq!(delta.changes, BTreeMap::from([(2, 14), (8, 3)]));
+        assert_eq!(delta.days(), 7.0);
+    }
+
+    #[test]
+    fn descale() {
+        let history = [
+            snapshot("2026-10-01T09:00:00Z", &[(7, 1)]),
+            snapshot("2026-10-05T09:00:00Z", &[(7, 2)]),
+            snapshot("2026-10-11T09:00:00Z", &[(7, 2)]),
+        ];
+        assert_eq!(
+            last_descale(&history, 7),
+            Some("2026-10-05T09:00:00Z".parse().unwrap())
+        );
+        assert_eq!(last_descale(&history[..1], 7), None);
+        assert_eq!(last_descale(&history, 8), None);
+    }
+}
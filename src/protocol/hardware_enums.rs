@@ -121,7 +121,7 @@ hardware_enum! {"Operation mode/trigger.", EcamOperationTrigger {
     DontCare = 0,
     /// Start preparing a beverage. This is the most likely enumeration value you'll want to use.
     Start = 1,
-    /// This is STARTPROGRAM and STOPV2, but only STOPV2 appears to be used.
+    /// This is STARTPROGRAM and STOPV2, but only STOPV2 appears to be used (ie: to stop a beverage being dispensed).
     StartProgramOrStopV2 = 2,
     NextStep = 3,
     Stop = 4,
@@ -132,9 +132,11 @@ hardware_enum! {"Operation mode/trigger.", EcamOperationTrigger {
 
 hardware_enum! {"Identifier determining the type of request and response (also referred to as the 'answer ID').", EcamRequestId {
     SetBtMode = 17,
+    /// Send a monitor V0 packet to the machine. Used by older machines, and untested.
     MonitorV0 = 96,
+    /// Send a monitor V1 packet to the machine. Used by older machines, and untested.
     MonitorV1 = 112,
-    /// Send a monitor V2 packet to the machine. This is the only tested and working monitor functionality.
+    /// Send a monitor V2 packet to the machine. This is the only monitor version tested against a real machine.
     MonitorV2 = 117,
     /// Brew a beverage, or interact with the profile saving functionality.
     BeverageDispensingMode = 131,
@@ -142,27 +144,38 @@ hardware_enum! {"Identifier determining the type of request and response (also r
     AppControl = 132,
     /// Read a parameter from the device. Used for reads less than or equal to 4 blocks, less than or equal to 10 blocks (each block is 2 bytes).
     ParameterRead = 149,
+    /// Write a parameter to the device. The acknowledgement is assumed to echo the parameter ID and data (unconfirmed).
     ParameterWrite = 144,
     /// Read a parameter from the device. Used for reads longer than 4 blocks, less than or equal to 10 blocks (each block is 2 bytes).
     ParameterReadExt = 161,
     StatisticsRead = 162,
+    /// Read a checksum that identifies the machine's firmware (the response layout is not known).
     Checksum = 163,
+    /// Read the names and icons for a range of profiles.
     ProfileNameRead = 164,
+    /// Write the name and icon for a single profile.
     ProfileNameWrite = 165,
     /// Read the default recipe for a beverage from the machine.
     RecipeQuantityRead = 166,
-    /// Read the priority order of beverages from the machine.
+    /// Read the priority order of beverages for the active profile from the machine.
     RecipePriorityRead = 168,
+    /// Select the active profile.
     ProfileSelection = 169,
+    /// Read the names and icons for a range of custom recipe slots.
     RecipeNameRead = 170,
+    /// Write the name and icon for a single custom recipe slot.
     RecipeNameWrite = 171,
+    /// Write the favorite beverages for a profile, in the order they should be shown on the display.
     SetFavoriteBeverages = 173,
     /// Request the min/max values for a given beverage. This may be a PIN operation in some other versions of the protocol.
     RecipeMinMaxSync = 176,
     PinSet = 177,
+    /// Select the active bean system on machines with the bean-adapt system.
     BeanSystemSelect = 185,
+    /// Read a range of bean systems (1-based, inclusive).
     BeanSystemRead = 186,
     BeanSystemWrite = 187,
+    /// Read the PIN lock state (the payload layout is inferred and untested).
     PinRead = 210,
     SetTime = 226,
 }}
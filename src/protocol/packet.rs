@@ -1,4 +1,4 @@
-use crate::protocol::request::{PartialDecode, PartialEncode};
+use crate::protocol::request::{DecodeError, PartialDecode, PartialEncode};
 use crc::Crc;
 use std::fmt::Debug;
 
@@ -34,29 +34,18 @@ impl EcamDriverPacket {
     }
 }
 
-/// A packet that may have a representation attached, allowing us to parse a packet once and only once.
+/// A packet that may have a representation attached, allowing us to parse a packet once and only once. Packets that
+/// fail to parse keep the raw bytes along with the [`DecodeError`].
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct EcamPacket<T> {
-    pub representation: Option<T>,
+    pub representation: Result<T, DecodeError>,
     pub bytes: EcamDriverPacket,
 }
 
-impl<T> EcamPacket<T> {
-    #[cfg(test)]
-    pub fn from_raw(input: &[u8]) -> EcamPacket<T> {
-        let bytes = EcamDriverPacket::from_vec(input.to_vec());
-        EcamPacket {
-            representation: None,
-            bytes,
-        }
-    }
-}
-
 impl<T: PartialDecode<T>> EcamPacket<T> {
-    pub fn from_bytes(mut input: &[u8]) -> EcamPacket<T> {
+    pub fn from_bytes(input: &[u8]) -> EcamPacket<T> {
         let bytes = EcamDriverPacket::from_vec(input.to_vec());
-        let input = &mut input;
-        let representation = <T>::partial_decode(input);
+        let representation = <T>::decode(input).0;
         EcamPacket {
             representation,
             bytes,
@@ -68,7 +57,7 @@ impl<T: PartialEncode> EcamPacket<T> {
     pub fn from_represenation(representation: T) -> EcamPacket<T> {
         let bytes = EcamDriverPacket::from_vec(representation.encode());
         EcamPacket {
-            representation: Some(representation),
+            representation: Ok(representation),
             bytes,
         }
     }
@@ -0,0 +1,54 @@
+use super::{
+    DecodeError, PartialDecode, PartialEncode, decode_field, decode_wide_string, encode_wide_string,
+};
+use crate::protocol::*;
+
+/// The settings stored for a type of bean on machines with the bean-adapt system, read with
+/// [`Request::BeanSystemRead`].
+///
+/// This layout is inferred and has not been tested against a real machine.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BeanSystem {
+    pub name: String,
+    pub temperature: MachineEnum<EcamTemperature>,
+    pub taste: MachineEnum<EcamBeverageTaste>,
+    pub grind: u8,
+}
+
+impl PartialDecode<BeanSystem> for BeanSystem {
+    fn partial_decode(input: &mut &[u8]) -> Result<BeanSystem, DecodeError> {
+        Ok(BeanSystem {
+            name: decode_wide_string(input, "name")?,
+            temperature: decode_field(input, "temperature")?,
+            taste: decode_field(input, "taste")?,
+            grind: decode_field(input, "grind")?,
+        })
+    }
+}
+
+impl PartialEncode for BeanSystem {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        encode_wide_string(&self.name, out);
+        out.push(self.temperature.into());
+        out.push(self.taste.into());
+        out.push(self.grind);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let bean = BeanSystem {
+            name: "Decaf".to_owned(),
+            temperature: EcamTemperature::High.into(),
+            taste: EcamBeverageTaste::Strong.into(),
+            grind: 3,
+        };
+        let encoded = bean.encode();
+        assert_eq!(encoded.len(), WIDE_STRING_LENGTH * 2 + 3);
+        assert_eq!(BeanSystem::decode(&encoded), (Ok(bean), [].as_slice()));
+    }
+}
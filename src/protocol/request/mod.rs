@@ -1,13 +1,16 @@
 mod app_control;
+mod bean;
 mod monitor;
 mod profile;
 mod recipe;
 
 use super::{hardware_enums::*, machine_enum::*};
 pub use app_control::*;
+pub use bean::*;
 pub use monitor::*;
 pub use profile::*;
 pub use recipe::*;
+use thiserror::Error;
 
 /// Implements the encode part of an encode/decode pair for a request or response.
 pub trait PartialEncode {
@@ -33,6 +36,13 @@ impl PartialEncode for u16 {
     }
 }
 
+impl PartialEncode for u32 {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        ((*self >> 16) as u16).partial_encode(out);
+        (*self as u16).partial_encode(out);
+    }
+}
+
 impl<T: PartialEncode> PartialEncode for Vec<T> {
     fn partial_encode(&self, out: &mut Vec<u8>) {
         for t in self.iter() {
@@ -47,57 +57,121 @@ impl<T: MachineEnumerable<T>> PartialEncode for &MachineEnum<T> {
     }
 }
 
+/// The reason a [`DecodeError`] occurred.
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum DecodeErrorReason {
+    #[error("unexpected end of packet")]
+    UnexpectedEnd,
+    #[error("unknown request ID {0:#04x}")]
+    UnknownRequestId(u8),
+    #[error("unknown ingredient {0:#04x}")]
+    UnknownIngredient(u8),
+    #[error("invalid character {0:#06x}")]
+    InvalidCharacter(u16),
+}
+
+/// An error that occurred while decoding a packet, recording which field failed and where.
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+#[error("failed to decode {field} at offset {offset}: {reason}")]
+pub struct DecodeError {
+    /// The path of the field that failed to decode, ie: `RecipeQuantityRead.ingredients.value`.
+    pub field: String,
+    /// The byte offset, from the start of the buffer passed to [`PartialDecode::decode`], at which decoding failed.
+    /// Errors returned directly from [`PartialDecode::partial_decode`] don't know the start of the buffer, and report
+    /// zero.
+    pub offset: usize,
+    pub reason: DecodeErrorReason,
+    /// The number of bytes left in the input when decoding failed, from which the offset is computed.
+    remaining: usize,
+}
+
+impl DecodeError {
+    /// Creates an error for the field starting at the current position of the input.
+    pub fn new(input: &[u8], reason: DecodeErrorReason) -> Self {
+        // The input is always a suffix of the buffer being decoded, so [`PartialDecode::decode`] can compute the offset
+        // from the number of bytes remaining
+        DecodeError {
+            field: String::new(),
+            offset: 0,
+            reason,
+            remaining: input.len(),
+        }
+    }
+
+    /// Prefixes the field path of this error with the name of the containing field.
+    pub fn in_field(mut self, field: &str) -> Self {
+        if self.field.is_empty() {
+            self.field = field.to_owned();
+        } else {
+            self.field = format!("{}.{}", field, self.field);
+        }
+        self
+    }
+}
+
 /// Implements the decode part of an encode/decode pair for a request or response.
 pub trait PartialDecode<T> {
     /// Partially decodes this type from a buffer, advancing the input slice to the next item.
-    fn partial_decode(input: &mut &[u8]) -> Option<T>;
+    fn partial_decode(input: &mut &[u8]) -> Result<T, DecodeError>;
 
     /// Decode a buffer fully, returning the unparsed remainder if available
-    fn decode(mut input: &[u8]) -> (Option<T>, &[u8]) {
-        let ret = Self::partial_decode(&mut input);
+    fn decode(mut input: &[u8]) -> (Result<T, DecodeError>, &[u8]) {
+        let len = input.len();
+        let ret = Self::partial_decode(&mut input).map_err(|mut e| {
+            e.offset = len - e.remaining;
+            e
+        });
         (ret, input)
     }
 }
 
+/// Decodes a named field, attaching the name of the field to any error.
+pub(crate) fn decode_field<T: PartialDecode<T>>(
+    input: &mut &[u8],
+    field: &str,
+) -> Result<T, DecodeError> {
+    T::partial_decode(input).map_err(|e| e.in_field(field))
+}
+
 impl<T: PartialDecode<T>> PartialDecode<Vec<T>> for Vec<T> {
-    fn partial_decode(input: &mut &[u8]) -> Option<Self> {
+    fn partial_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
         let mut v = vec![];
         while !input.is_empty() {
             v.push(<T>::partial_decode(input)?);
         }
-        Some(v)
+        Ok(v)
     }
 }
 
 impl<T: MachineEnumerable<T>> PartialDecode<MachineEnum<T>> for MachineEnum<T> {
-    fn partial_decode(input: &mut &[u8]) -> Option<Self> {
-        let (head, tail) = input.split_first()?;
-        *input = tail;
-        Some(MachineEnum::decode(*head))
+    fn partial_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(MachineEnum::decode(<u8>::partial_decode(input)?))
     }
 }
 
 impl PartialDecode<u8> for u8 {
-    fn partial_decode(input: &mut &[u8]) -> Option<u8> {
-        let (head, tail) = input.split_first()?;
+    fn partial_decode(input: &mut &[u8]) -> Result<u8, DecodeError> {
+        let (head, tail) = input
+            .split_first()
+            .ok_or_else(|| DecodeError::new(input, DecodeErrorReason::UnexpectedEnd))?;
         *input = tail;
-        Some(*head)
+        Ok(*head)
     }
 }
 
 impl PartialDecode<u16> for u16 {
-    fn partial_decode(input: &mut &[u8]) -> Option<u16> {
+    fn partial_decode(input: &mut &[u8]) -> Result<u16, DecodeError> {
         let a = <u8>::partial_decode(input)? as u16;
         let b = <u8>::partial_decode(input)? as u16;
-        Some((a << 8) | b)
+        Ok((a << 8) | b)
     }
 }
 
 impl PartialDecode<u32> for u32 {
-    fn partial_decode(input: &mut &[u8]) -> Option<u32> {
+    fn partial_decode(input: &mut &[u8]) -> Result<u32, DecodeError> {
         let a = <u16>::partial_decode(input)? as u32;
         let b = <u16>::partial_decode(input)? as u32;
-        Some((a << 16) | b)
+        Ok((a << 16) | b)
     }
 }
 
@@ -167,28 +241,25 @@ macro_rules! packet_definition {
         }
 
         impl PartialDecode<Response> for Response {
-            fn partial_decode(input: &mut &[u8]) -> Option<Self> {
-                if input.len() < 2 {
-                    return None;
-                }
-                let id = EcamRequestId::try_from(input[0]);
-                if let Ok(id) = id {
-                    let _ = input[1];
-                    *input = &input[2..];
-                    match id {
-                        $(
-                            EcamRequestId::$name => {
-                                $(
-                                    let $resp_name = <$resp_type>::partial_decode(input)?;
-                                )*
-                                return Some(Self::$name(
-                                    $( $resp_name ),*
-                                ));
-                            }
-                        )*
-                    }
+            fn partial_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let start = *input;
+                let id = decode_field::<u8>(input, "id")?;
+                let id = EcamRequestId::try_from(id).map_err(|_| {
+                    DecodeError::new(start, DecodeErrorReason::UnknownRequestId(id)).in_field("id")
+                })?;
+                let _ = decode_field::<u8>(input, "flags")?;
+                match id {
+                    $(
+                        EcamRequestId::$name => {
+                            $(
+                                let $resp_name = decode_field::<$resp_type>(input, concat!(stringify!($name), ".", stringify!($resp_name)))?;
+                            )*
+                            Ok(Self::$name(
+                                $( $resp_name ),*
+                            ))
+                        }
+                    )*
                 }
-                None
             }
         }
     };
@@ -196,8 +267,8 @@ macro_rules! packet_definition {
 
 packet_definition!(
     SetBtMode() => (),
-    MonitorV0() => (),
-    MonitorV1() => (),
+    MonitorV0() => (response MonitorV0Response),
+    MonitorV1() => (response MonitorV1Response),
     MonitorV2() => (response MonitorV2Response),
     BeverageDispensingMode(
         recipe MachineEnum<EcamBeverageId>,
@@ -206,25 +277,25 @@ packet_definition!(
         mode MachineEnum<EcamBeverageTasteType>) => (unknown0 u8, unknown1 u8),
     AppControl(request AppControl) => (),
     ParameterRead(parameter u16, len u8) => (),
-    ParameterWrite() => (),
+    ParameterWrite(parameter u16, data Vec<u8>) => (parameter u16, data Vec<u8>),
     ParameterReadExt(parameter u16, len u8) => (parameter u16, data Vec<u8>),
     StatisticsRead(parameter u16, len u8) => (data Vec<Statistic>),
-    Checksum() => (),
+    Checksum() => (checksum Vec<u8>),
     ProfileNameRead(start u8, end u8) => (names Vec<WideStringWithIcon>),
-    ProfileNameWrite() => (),
+    ProfileNameWrite(profile u8, name WideStringWithIcon) => (),
     RecipeQuantityRead(profile u8, recipe MachineEnum<EcamBeverageId>)
         => (profile u8, recipe MachineEnum<EcamBeverageId>, ingredients Vec<RecipeInfo<u16>>),
     RecipePriorityRead() => (priorities Vec<u8>),
-    ProfileSelection() => (),
+    ProfileSelection(profile u8) => (),
     RecipeNameRead(start u8, end u8) => (names Vec<WideStringWithIcon>),
-    RecipeNameWrite() => (),
+    RecipeNameWrite(recipe u8, name WideStringWithIcon) => (),
     SetFavoriteBeverages(profile u8, recipies Vec<u8>) => (),
     RecipeMinMaxSync(recipe MachineEnum<EcamBeverageId>) => (recipe MachineEnum<EcamBeverageId>, bounds Vec<RecipeMinMaxInfo>),
     PinSet() => (),
-    BeanSystemSelect() => (),
-    BeanSystemRead() => (),
+    BeanSystemSelect(bean u8) => (),
+    BeanSystemRead(start u8, end u8) => (beans Vec<BeanSystem>),
     BeanSystemWrite() => (),
-    PinRead() => (),
+    PinRead() => (enabled u8),
     SetTime() => (),
 );
 
@@ -239,6 +310,20 @@ impl Request {
                 | Request::StatisticsRead(..)
         )
     }
+
+    /// Is the given [`Response`] the answer to this request? The response must have the same [`EcamRequestId`], and
+    /// responses that echo part of the request (ie: the parameter or beverage) must echo the same values.
+    pub fn matches_response(&self, response: &Response) -> bool {
+        match (self, response) {
+            (Request::ParameterWrite(a, _), Response::ParameterWrite(b, _))
+            | (Request::ParameterReadExt(a, _), Response::ParameterReadExt(b, _)) => a == b,
+            (Request::RecipeQuantityRead(p1, r1), Response::RecipeQuantityRead(p2, r2, _)) => {
+                p1 == p2 && r1 == r2
+            }
+            (Request::RecipeMinMaxSync(a), Response::RecipeMinMaxSync(b, _)) => a == b,
+            _ => self.ecam_request_id() == response.ecam_request_id(),
+        }
+    }
 }
 
 /// A statistic read from the device.
@@ -249,10 +334,17 @@ pub struct Statistic {
 }
 
 impl PartialDecode<Statistic> for Statistic {
-    fn partial_decode(input: &mut &[u8]) -> Option<Self> {
-        let stat = <u16>::partial_decode(input)?;
-        let value = <u32>::partial_decode(input)?;
-        Some(Statistic { stat, value })
+    fn partial_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let stat = decode_field::<u16>(input, "stat")?;
+        let value = decode_field::<u32>(input, "value")?;
+        Ok(Statistic { stat, value })
+    }
+}
+
+impl PartialEncode for Statistic {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        self.stat.partial_encode(out);
+        self.value.partial_encode(out);
     }
 }
 
@@ -274,11 +366,35 @@ mod test {
     fn real_packets_decode_as_expected(#[case] bytes: &[u8]) {
         let (packet, remainder) = Response::decode(unwrap_packet(bytes));
         let packet = packet.expect("Expected to decode something");
-        assert_eq!(remainder, &[]);
+        assert_eq!(remainder, &[] as &[u8]);
         // Not actually testing the decoding of these packets, but at least we can print it
         println!("{:?}", packet);
     }
 
+    #[rstest]
+    #[case(&[117, 15, 1, 5], "MonitorV2.response.switches", 4, DecodeErrorReason::UnexpectedEnd)]
+    #[case(&[0xff, 0xf0], "id", 0, DecodeErrorReason::UnknownRequestId(0xff))]
+    #[case(&[166, 0xf0, 1, 1, 1, 0, 40, 0x40, 0], "RecipeQuantityRead.ingredients.ingredient", 7, DecodeErrorReason::UnknownIngredient(0x40))]
+    #[case(&[166, 0xf0, 1, 1, 1, 0], "RecipeQuantityRead.ingredients.value", 6, DecodeErrorReason::UnexpectedEnd)]
+    #[case(&[164, 0xf0, 0, 65, 0xdc, 0], "ProfileNameRead.names.name", 4, DecodeErrorReason::InvalidCharacter(0xdc00))]
+    fn test_decode_error(
+        #[case] bytes: &[u8],
+        #[case] field: &str,
+        #[case] offset: usize,
+        #[case] reason: DecodeErrorReason,
+    ) {
+        let (packet, _) = Response::decode(bytes);
+        assert_eq!(
+            packet,
+            Err(DecodeError {
+                field: field.to_owned(),
+                offset,
+                reason,
+                remaining: bytes.len() - offset,
+            })
+        );
+    }
+
     #[test]
     fn test_decode_monitor_packet() {
         let buf = [117_u8, 15, 1, 5, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0];
@@ -340,6 +456,134 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_parameter_write() {
+        assert_eq!(
+            Request::ParameterWrite(0x3e, vec![0, 0, 0, 30]).encode(),
+            vec![0x90, 0xf0, 0x00, 0x3e, 0x00, 0x00, 0x00, 0x1e]
+        );
+        let buf = [0x90_u8, 0xf0, 0x00, 0x3e, 0x00, 0x00, 0x00, 0x1e];
+        assert_eq!(
+            <Response>::decode(&buf),
+            (
+                Ok(Response::ParameterWrite(0x3e, vec![0, 0, 0, 30])),
+                [].as_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_profile_write() {
+        let mut expected = vec![165_u8, 240, 2, 0, 77, 0, 105, 0, 97];
+        expected.extend_from_slice(&[0; 14]);
+        expected.push(8);
+        assert_eq!(
+            Request::ProfileNameWrite(2, WideStringWithIcon::new("Mia", 8)).encode(),
+            expected
+        );
+        assert_eq!(Request::ProfileSelection(2).encode(), vec![169, 240, 2]);
+    }
+
+    #[test]
+    fn test_recipe_name_write() {
+        let mut expected = vec![171_u8, 240, 1, 0, 77, 0, 105, 0, 97];
+        expected.extend_from_slice(&[0; 14]);
+        expected.push(3);
+        assert_eq!(
+            Request::RecipeNameWrite(1, WideStringWithIcon::new("Mia", 3)).encode(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_save_and_reset_recipe() {
+        assert_eq!(
+            Request::BeverageDispensingMode(
+                EcamBeverageId::EspressoCoffee.into(),
+                EcamOperationTrigger::DontCare.into(),
+                vec![RecipeInfo::new(EcamIngredients::Coffee, 60)],
+                EcamBeverageTasteType::Save.into()
+            )
+            .encode(),
+            vec![0x83, 0xf0, 0x01, 0x00, 0x01, 0x00, 0x3c, 0x01]
+        );
+        assert_eq!(
+            Request::BeverageDispensingMode(
+                EcamBeverageId::EspressoCoffee.into(),
+                EcamOperationTrigger::DontCare.into(),
+                vec![],
+                EcamBeverageTasteType::Delete.into()
+            )
+            .encode(),
+            vec![0x83, 0xf0, 0x01, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_matches_response() {
+        let req = Request::RecipeQuantityRead(1, EcamBeverageId::Cappuccino.into());
+        assert!(req.matches_response(&Response::RecipeQuantityRead(
+            1,
+            EcamBeverageId::Cappuccino.into(),
+            vec![]
+        )));
+        assert!(!req.matches_response(&Response::RecipeQuantityRead(
+            2,
+            EcamBeverageId::Cappuccino.into(),
+            vec![]
+        )));
+        assert!(!req.matches_response(&Response::RecipeQuantityRead(
+            1,
+            EcamBeverageId::EspressoCoffee.into(),
+            vec![]
+        )));
+        assert!(!req.matches_response(&Response::RecipeMinMaxSync(
+            EcamBeverageId::Cappuccino.into(),
+            vec![]
+        )));
+        let req = Request::ParameterWrite(0x3e, vec![0, 0, 0, 30]);
+        assert!(req.matches_response(&Response::ParameterWrite(0x3e, vec![0, 0, 0, 30])));
+        assert!(!req.matches_response(&Response::ParameterWrite(0x3f, vec![0, 0, 0, 30])));
+        assert!(Request::ProfileSelection(2).matches_response(&Response::ProfileSelection()));
+    }
+
+    #[test]
+    fn test_pin() {
+        // Only the first byte is decoded, the layout of the rest is unknown
+        let (packet, remainder) = Response::decode(&[0xd2, 0xf0, 0, 0, 0]);
+        assert_eq!(packet, Ok(Response::PinRead(0)));
+        assert_eq!(remainder, &[0, 0]);
+    }
+
+    #[test]
+    fn test_set_favorite_beverages() {
+        assert_eq!(
+            Request::SetFavoriteBeverages(
+                2,
+                vec![
+                    EcamBeverageId::Cappuccino.into(),
+                    EcamBeverageId::EspressoCoffee.into()
+                ]
+            )
+            .encode(),
+            vec![0xad, 0xf0, 2, 7, 1]
+        );
+    }
+
+    #[test]
+    fn test_stop_coffee() {
+        assert_eq!(
+            Request::BeverageDispensingMode(
+                EcamBeverageId::RegularCoffee.into(),
+                EcamOperationTrigger::StartProgramOrStopV2.into(),
+                vec![],
+                EcamBeverageTasteType::Prepare.into()
+            )
+            .encode(),
+            vec![0x83, 0xf0, 0x02, 0x02, 0x02]
+        );
+    }
+
     #[test]
     fn test_brew_coffee() {
         let recipe = vec![
@@ -1,4 +1,4 @@
-use super::PartialDecode;
+use super::{DecodeError, PartialDecode, decode_field};
 use crate::protocol::*;
 
 /// The response to a monitor inquiry sent by [`Request::MonitorV2`].
@@ -20,38 +20,224 @@ pub struct MonitorV2Response {
 }
 
 impl<T: MachineEnumerable<T>> PartialDecode<SwitchSet<T>> for SwitchSet<T> {
-    fn partial_decode(input: &mut &[u8]) -> Option<SwitchSet<T>> {
+    fn partial_decode(input: &mut &[u8]) -> Result<SwitchSet<T>, DecodeError> {
         let a = <u8>::partial_decode(input)? as u16;
         let b = <u8>::partial_decode(input)? as u16;
         // Note that this is inverted from <u16>::partial_decode
-        Some(SwitchSet::from_u16((b << 8) | a))
+        Ok(SwitchSet::from_u16((b << 8) | a))
     }
 }
 
 impl<T: MachineEnumerable<T>> PartialEncode for SwitchSet<T> {
     fn partial_encode(&self, out: &mut Vec<u8>) {
-        self.value.partial_encode(out)
+        // Mirrors the inverted byte order in partial_decode
+        out.push(self.value as u8);
+        out.push((self.value >> 8) as u8);
     }
 }
 
 impl PartialDecode<MonitorV2Response> for MonitorV2Response {
-    fn partial_decode(input: &mut &[u8]) -> Option<MonitorV2Response> {
-        Some(MonitorV2Response {
-            accessory: <MachineEnum<EcamAccessory>>::partial_decode(input)?,
-            switches: <SwitchSet<EcamMachineSwitch>>::partial_decode(input)?,
-            alarms: <SwitchSet<EcamMachineAlarm>>::partial_decode(input)?,
-            state: <MachineEnum<EcamMachineState>>::partial_decode(input)?,
-            progress: <u8>::partial_decode(input)?,
-            percentage: <u8>::partial_decode(input)?,
-            unknown0: <u8>::partial_decode(input)?,
-            unknown1: <u8>::partial_decode(input)?,
-            unknown2: <u8>::partial_decode(input)?,
-            unknown3: <u8>::partial_decode(input)?,
-            unknown4: <u8>::partial_decode(input)?,
+    fn partial_decode(input: &mut &[u8]) -> Result<MonitorV2Response, DecodeError> {
+        Ok(MonitorV2Response {
+            accessory: decode_field(input, "accessory")?,
+            switches: decode_field(input, "switches")?,
+            alarms: decode_field(input, "alarms")?,
+            state: decode_field(input, "state")?,
+            progress: decode_field(input, "progress")?,
+            percentage: decode_field(input, "percentage")?,
+            unknown0: decode_field(input, "unknown0")?,
+            unknown1: decode_field(input, "unknown1")?,
+            unknown2: decode_field(input, "unknown2")?,
+            unknown3: decode_field(input, "unknown3")?,
+            unknown4: decode_field(input, "unknown4")?,
         })
     }
 }
 
+/// The response to a monitor inquiry sent by [`Request::MonitorV0`], used by older machines. Switches and alarms are
+/// a single byte each.
+///
+/// This layout is inferred from [`MonitorV2Response`] and has not been tested against a real machine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MonitorV0Response {
+    pub state: MachineEnum<EcamMachineState>,
+    pub accessory: MachineEnum<EcamAccessory>,
+    pub switches: SwitchSet<EcamMachineSwitch>,
+    pub alarms: SwitchSet<EcamMachineAlarm>,
+    pub progress: u8,
+    pub percentage: u8,
+}
+
+/// Decodes a single-byte [`SwitchSet`].
+fn decode_narrow_switches<T: MachineEnumerable<T>>(
+    input: &mut &[u8],
+    field: &str,
+) -> Result<SwitchSet<T>, DecodeError> {
+    Ok(SwitchSet::from_u16(decode_field::<u8>(input, field)? as u16))
+}
+
+impl PartialDecode<MonitorV0Response> for MonitorV0Response {
+    fn partial_decode(input: &mut &[u8]) -> Result<MonitorV0Response, DecodeError> {
+        Ok(MonitorV0Response {
+            accessory: decode_field(input, "accessory")?,
+            switches: decode_narrow_switches(input, "switches")?,
+            alarms: decode_narrow_switches(input, "alarms")?,
+            state: decode_field(input, "state")?,
+            progress: decode_field(input, "progress")?,
+            percentage: decode_field(input, "percentage")?,
+        })
+    }
+}
+
+impl PartialEncode for MonitorV0Response {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        out.push(self.accessory.into());
+        out.push(self.switches.value as u8);
+        out.push(self.alarms.value as u8);
+        out.push(self.state.into());
+        out.push(self.progress);
+        out.push(self.percentage);
+    }
+}
+
+/// The response to a monitor inquiry sent by [`Request::MonitorV1`]. This is the same as [`MonitorV2Response`], but
+/// without the trailing fields.
+///
+/// This layout is inferred from [`MonitorV2Response`] and has not been tested against a real machine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MonitorV1Response {
+    pub state: MachineEnum<EcamMachineState>,
+    pub accessory: MachineEnum<EcamAccessory>,
+    pub switches: SwitchSet<EcamMachineSwitch>,
+    pub alarms: SwitchSet<EcamMachineAlarm>,
+    pub progress: u8,
+    pub percentage: u8,
+}
+
+impl PartialDecode<MonitorV1Response> for MonitorV1Response {
+    fn partial_decode(input: &mut &[u8]) -> Result<MonitorV1Response, DecodeError> {
+        Ok(MonitorV1Response {
+            accessory: decode_field(input, "accessory")?,
+            switches: decode_field(input, "switches")?,
+            alarms: decode_field(input, "alarms")?,
+            state: decode_field(input, "state")?,
+            progress: decode_field(input, "progress")?,
+            percentage: decode_field(input, "percentage")?,
+        })
+    }
+}
+
+impl PartialEncode for MonitorV1Response {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        out.push(self.accessory.into());
+        self.switches.partial_encode(out);
+        self.alarms.partial_encode(out);
+        out.push(self.state.into());
+        out.push(self.progress);
+        out.push(self.percentage);
+    }
+}
+
+/// The version of the monitor protocol spoken by a machine.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MonitorVersion {
+    V0,
+    V1,
+    V2,
+}
+
+impl MonitorVersion {
+    /// All monitor versions, in the order they should be probed (newest first).
+    pub const PROBE_ORDER: [MonitorVersion; 3] =
+        [MonitorVersion::V2, MonitorVersion::V1, MonitorVersion::V0];
+
+    /// The [`Request`] used to poll the machine's status with this version.
+    pub fn request(&self) -> Request {
+        match self {
+            MonitorVersion::V0 => Request::MonitorV0(),
+            MonitorVersion::V1 => Request::MonitorV1(),
+            MonitorVersion::V2 => Request::MonitorV2(),
+        }
+    }
+}
+
+/// A version-neutral view of the machine's status, built from any of the monitor responses.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MonitorStatus {
+    pub version: Option<MonitorVersion>,
+    pub state: MachineEnum<EcamMachineState>,
+    pub accessory: MachineEnum<EcamAccessory>,
+    pub switches: SwitchSet<EcamMachineSwitch>,
+    pub alarms: SwitchSet<EcamMachineAlarm>,
+    pub progress: u8,
+    pub percentage: u8,
+    /// Fields that have not been decoded yet. Only [`MonitorV2Response`] has these.
+    pub unknown: Vec<u8>,
+}
+
+impl MonitorStatus {
+    /// Extracts the status from a monitor [`Response`], if this is one.
+    pub fn from_response(response: &Response) -> Option<MonitorStatus> {
+        match response {
+            Response::MonitorV0(response) => Some(response.clone().into()),
+            Response::MonitorV1(response) => Some(response.clone().into()),
+            Response::MonitorV2(response) => Some(response.clone().into()),
+            _ => None,
+        }
+    }
+}
+
+impl From<MonitorV0Response> for MonitorStatus {
+    fn from(response: MonitorV0Response) -> Self {
+        MonitorStatus {
+            version: Some(MonitorVersion::V0),
+            state: response.state,
+            accessory: response.accessory,
+            switches: response.switches,
+            alarms: response.alarms,
+            progress: response.progress,
+            percentage: response.percentage,
+            unknown: vec![],
+        }
+    }
+}
+
+impl From<MonitorV1Response> for MonitorStatus {
+    fn from(response: MonitorV1Response) -> Self {
+        MonitorStatus {
+            version: Some(MonitorVersion::V1),
+            state: response.state,
+            accessory: response.accessory,
+            switches: response.switches,
+            alarms: response.alarms,
+            progress: response.progress,
+            percentage: response.percentage,
+            unknown: vec![],
+        }
+    }
+}
+
+impl From<MonitorV2Response> for MonitorStatus {
+    fn from(response: MonitorV2Response) -> Self {
+        MonitorStatus {
+            version: Some(MonitorVersion::V2),
+            state: response.state,
+            accessory: response.accessory,
+            switches: response.switches,
+            alarms: response.alarms,
+            progress: response.progress,
+            percentage: response.percentage,
+            unknown: vec![
+                response.unknown0,
+                response.unknown1,
+                response.unknown2,
+                response.unknown3,
+                response.unknown4,
+            ],
+        }
+    }
+}
+
 impl PartialEncode for MonitorV2Response {
     fn partial_encode(&self, out: &mut Vec<u8>) {
         out.push(self.accessory.into());
@@ -70,9 +256,65 @@ impl PartialEncode for MonitorV2Response {
 
 #[cfg(test)]
 mod test {
-    use crate::protocol::EcamMachineSwitch;
+    use crate::protocol::*;
+
+    #[test]
+    fn switch_set_round_trip() {
+        let switches =
+            SwitchSet::of(&[EcamMachineSwitch::WaterSpout, EcamMachineSwitch::IfdCaraffe]);
+        let encoded = switches.encode();
+        assert_eq!(encoded, vec![0x01, 0x01]);
+        assert_eq!(
+            SwitchSet::<EcamMachineSwitch>::decode(&encoded),
+            (Ok(switches), [].as_slice())
+        );
+    }
 
-    use super::SwitchSet;
+    #[test]
+    fn monitor_versions_agree() {
+        let v0 = MonitorV0Response {
+            state: EcamMachineState::ReadyOrDispensing.into(),
+            accessory: EcamAccessory::Water.into(),
+            switches: SwitchSet::of(&[EcamMachineSwitch::WaterSpout]),
+            alarms: SwitchSet::of(&[EcamMachineAlarm::EmptyWaterTank]),
+            progress: 1,
+            percentage: 50,
+        };
+        let v1 = MonitorV1Response {
+            state: v0.state,
+            accessory: v0.accessory,
+            switches: v0.switches,
+            alarms: v0.alarms,
+            progress: v0.progress,
+            percentage: v0.percentage,
+        };
+        let v2 = MonitorV2Response {
+            state: v0.state,
+            accessory: v0.accessory,
+            switches: v0.switches,
+            alarms: v0.alarms,
+            progress: v0.progress,
+            percentage: v0.percentage,
+            ..Default::default()
+        };
+        assert_eq!(v0.encode(), vec![1, 1, 1, 7, 1, 50]);
+        assert_eq!(v1.encode(), vec![1, 1, 0, 1, 0, 7, 1, 50]);
+        assert_eq!(MonitorV0Response::decode(&v0.encode()).0, Ok(v0.clone()));
+        assert_eq!(MonitorV1Response::decode(&v1.encode()).0, Ok(v1.clone()));
+
+        let status = MonitorStatus::from(v2);
+        assert_eq!(status.unknown, vec![0; 5]);
+        for other in [MonitorStatus::from(v0), MonitorStatus::from(v1)] {
+            assert_eq!(
+                MonitorStatus {
+                    version: status.version,
+                    unknown: status.unknown.clone(),
+                    ..other
+                },
+                status
+            );
+        }
+    }
 
     #[test]
     fn switch_set_test() {
@@ -1,14 +1,17 @@
-use super::PartialDecode;
+use super::{DecodeError, DecodeErrorReason, PartialDecode, PartialEncode, decode_field};
+
+/// The number of wide characters in a [`WideStringWithIcon`].
+pub(crate) const WIDE_STRING_LENGTH: usize = 10;
 
 /// Represents a recipe or profile name with an associate icon tucked into the last byte.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct WideStringWithIcon {
-    name: String,
-    icon: u8,
+    pub name: String,
+    pub icon: u8,
 }
 
 impl WideStringWithIcon {
-    #[cfg(test)]
+    /// Creates a new name and icon pair. Names longer than ten characters will be truncated when encoded.
     pub fn new(name: &str, icon: u8) -> Self {
         WideStringWithIcon {
             name: name.to_owned(),
@@ -17,22 +20,74 @@ impl WideStringWithIcon {
     }
 }
 
+/// Decodes a fixed-length, zero-padded string of [`WIDE_STRING_LENGTH`] big-endian wide characters.
+pub(crate) fn decode_wide_string(input: &mut &[u8], field: &str) -> Result<String, DecodeError> {
+    let mut s = vec![];
+    for _ in 0..WIDE_STRING_LENGTH {
+        let start = *input;
+        let c = decode_field::<u16>(input, field)?;
+        let char = char::from_u32(c as u32).ok_or_else(|| {
+            DecodeError::new(start, DecodeErrorReason::InvalidCharacter(c)).in_field(field)
+        })?;
+        s.push(char);
+    }
+    Ok(s.iter()
+        .collect::<String>()
+        .trim_end_matches(['\0'])
+        .to_owned())
+}
+
+/// Encodes a string as [`WIDE_STRING_LENGTH`] big-endian wide characters, truncating or zero-padding as needed.
+pub(crate) fn encode_wide_string(s: &str, out: &mut Vec<u8>) {
+    let mut chars = s.chars();
+    for _ in 0..WIDE_STRING_LENGTH {
+        // Characters outside of the basic multilingual plane can't be represented in a single wide char
+        let c = chars
+            .next()
+            .map_or(0, |c| u16::try_from(c as u32).unwrap_or('?' as u16));
+        c.partial_encode(out);
+    }
+}
+
 impl PartialDecode<WideStringWithIcon> for WideStringWithIcon {
-    fn partial_decode(input: &mut &[u8]) -> Option<WideStringWithIcon> {
-        let mut s = vec![];
-        for _ in 0..10 {
-            let b1 = <u8>::partial_decode(input)? as u16;
-            let b2 = <u8>::partial_decode(input)? as u16;
-            let char = char::from_u32(((b1 << 8) | b2) as u32).expect("Invalid character");
-            s.push(char);
-        }
-        Some(WideStringWithIcon {
-            name: s
-                .iter()
-                .collect::<String>()
-                .trim_end_matches(['\0'])
-                .to_owned(),
-            icon: <u8>::partial_decode(input)?,
+    fn partial_decode(input: &mut &[u8]) -> Result<WideStringWithIcon, DecodeError> {
+        Ok(WideStringWithIcon {
+            name: decode_wide_string(input, "name")?,
+            icon: decode_field(input, "icon")?,
         })
     }
 }
+
+impl PartialEncode for WideStringWithIcon {
+    fn partial_encode(&self, out: &mut Vec<u8>) {
+        encode_wide_string(&self.name, out);
+        out.push(self.icon);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("Matt", 3)]
+    #[case("", 0)]
+    #[case("PROFILE 10", 8)]
+    #[case("Café ☕", 1)]
+    fn round_trip(#[case] name: &str, #[case] icon: u8) {
+        let s = WideStringWithIcon::new(name, icon);
+        let encoded = s.encode();
+        assert_eq!(encoded.len(), WIDE_STRING_LENGTH * 2 + 1);
+        assert_eq!(WideStringWithIcon::decode(&encoded), (Ok(s), [].as_slice()));
+    }
+
+    #[test]
+    fn truncate() {
+        let encoded = WideStringWithIcon::new("A very long profile name", 2).encode();
+        assert_eq!(
+            WideStringWithIcon::decode(&encoded).0,
+            Ok(WideStringWithIcon::new("A very lon", 2))
+        );
+    }
+}
@@ -1,5 +1,35 @@
+use super::decode_field;
 use crate::protocol::*;
 
+/// Decodes an ingredient, returning it along with whether its values are encoded as two bytes.
+fn decode_ingredient(
+    input: &mut &[u8],
+) -> Result<(MachineEnum<EcamIngredients>, bool), DecodeError> {
+    let start = *input;
+    let ingredient = decode_field::<MachineEnum<EcamIngredients>>(input, "ingredient")?;
+    let wide = match ingredient {
+        MachineEnum::Value(known) => known.is_wide_encoding(),
+        MachineEnum::Unknown(_) => None,
+    };
+    match wide {
+        Some(wide) => Ok((ingredient, wide)),
+        None => Err(DecodeError::new(
+            start,
+            DecodeErrorReason::UnknownIngredient(ingredient.into()),
+        )
+        .in_field("ingredient")),
+    }
+}
+
+/// Decodes an ingredient value that is either one or two bytes wide.
+fn decode_value(input: &mut &[u8], wide: bool, field: &str) -> Result<u16, DecodeError> {
+    if wide {
+        decode_field::<u16>(input, field)
+    } else {
+        Ok(decode_field::<u8>(input, field)? as u16)
+    }
+}
+
 /// Recipe information returned from [`Request::RecipeQuantityRead`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct RecipeInfo<T> {
@@ -17,22 +47,12 @@ impl<T: Copy + Clone + Eq + PartialEq> RecipeInfo<T> {
 }
 
 impl PartialDecode<RecipeInfo<u16>> for RecipeInfo<u16> {
-    fn partial_decode(input: &mut &[u8]) -> Option<Self> {
-        let ingredient = <MachineEnum<EcamIngredients>>::partial_decode(input)?;
-        if let MachineEnum::Value(known) = ingredient {
-            if known.is_wide_encoding().expect("Unknown encoding") {
-                return Some(RecipeInfo {
-                    ingredient,
-                    value: <u16>::partial_decode(input)?,
-                });
-            } else {
-                return Some(RecipeInfo {
-                    ingredient,
-                    value: <u8>::partial_decode(input)? as u16,
-                });
-            }
-        }
-        panic!("Unhandled ingredient {:?}", ingredient);
+    fn partial_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let (ingredient, wide) = decode_ingredient(input)?;
+        Ok(RecipeInfo {
+            ingredient,
+            value: decode_value(input, wide, "value")?,
+        })
     }
 }
 
@@ -60,29 +80,14 @@ pub struct RecipeMinMaxInfo {
 }
 
 impl PartialDecode<RecipeMinMaxInfo> for RecipeMinMaxInfo {
-    fn partial_decode(input: &mut &[u8]) -> Option<Self> {
-        let ingredient = <MachineEnum<EcamIngredients>>::partial_decode(input)?;
-        if let MachineEnum::Value(known) = ingredient {
-            if known
-                .is_wide_encoding()
-                .unwrap_or_else(|| panic!("Unknown encoding for {:?}", known))
-            {
-                return Some(RecipeMinMaxInfo {
-                    ingredient,
-                    min: <u16>::partial_decode(input)?,
-                    value: <u16>::partial_decode(input)?,
-                    max: <u16>::partial_decode(input)?,
-                });
-            } else {
-                return Some(RecipeMinMaxInfo {
-                    ingredient,
-                    min: <u8>::partial_decode(input)? as u16,
-                    value: <u8>::partial_decode(input)? as u16,
-                    max: <u8>::partial_decode(input)? as u16,
-                });
-            }
-        }
-        panic!("Unhandled ingredient {:?}", ingredient);
+    fn partial_decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let (ingredient, wide) = decode_ingredient(input)?;
+        Ok(RecipeMinMaxInfo {
+            ingredient,
+            min: decode_value(input, wide, "min")?,
+            value: decode_value(input, wide, "value")?,
+            max: decode_value(input, wide, "max")?,
+        })
     }
 }
 
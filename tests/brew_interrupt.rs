@@ -0,0 +1,64 @@
+//! Interrupting `brew` the way a terminal does on Ctrl+C: SIGINT is sent to the whole foreground process group.
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
+#[test]
+fn brew_sends_stop_after_ctrl_c() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_longshot"))
+        .args([
+            "--trace",
+            "brew",
+            "--device-name",
+            "sim[on]",
+            "--beverage",
+            "regularcoffee",
+            "--coffee",
+            "180",
+            "--taste",
+            "normal",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .expect("Failed to start longshot");
+
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr = std::thread::spawn(move || {
+        let mut s = String::new();
+        stderr.read_to_string(&mut s).unwrap();
+        s
+    });
+
+    // Wait until the beverage is being dispensed, then interrupt the process group
+    let mut stdout = vec![];
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    for line in lines.by_ref() {
+        let line = line.unwrap();
+        let dispensing = line.starts_with("Busy(");
+        stdout.push(line);
+        if dispensing {
+            break;
+        }
+    }
+    let status = Command::new("kill")
+        .args(["-INT", "--", &format!("-{}", child.id())])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    stdout.extend(lines.map(Result::unwrap));
+    let status = child.wait().unwrap();
+    let stderr = stderr.join().unwrap();
+    assert!(status.success(), "{}\n{}", stdout.join("\n"), stderr);
+    assert!(
+        stdout.contains(&"Stopped".to_owned()),
+        "{}",
+        stdout.join("\n")
+    );
+    // BeverageDispensingMode(RegularCoffee, StartProgramOrStopV2, [], Prepare)
+    assert!(stderr.contains("{host->device} |83f0020202|"), "{}", stderr);
+}